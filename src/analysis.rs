@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{satisfy, Bdd, EvalError, Expr, Program, VarOrder, TABLE_LIMIT};
+
+/// Whether a formula is always, never or sometimes true
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Classification {
+    /// True under every assignment
+    Tautology,
+    /// False under every assignment
+    Contradiction,
+    /// True under some assignments and false under others
+    Contingent,
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Classification::Tautology => write!(f, "tautology"),
+            Classification::Contradiction => write!(f, "contradiction"),
+            Classification::Contingent => write!(f, "contingent"),
+        }
+    }
+}
+
+/// The classification of a formula with the assignments proving it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub class: Classification,
+    /// An assignment making the formula true, if there is one
+    pub satisfying: Option<HashMap<String, bool>>,
+    /// An assignment making the formula false, if there is one
+    pub falsifying: Option<HashMap<String, bool>>,
+}
+
+/// Finds out whether `expr` is a tautology, a contradiction or contingent
+///
+/// Formulas with few enough variables for a truth table are compiled into a [`Bdd`],
+/// which is a constant exactly for tautologies and contradictions, instead of going
+/// through the table. The larger ones, whose diagrams may blow up, go to the SAT solver.
+pub fn classify(expr: &Expr) -> Result<Analysis, EvalError> {
+    classify_in(&Program::default(), expr)
+}
+
+/// Like [`classify`] for a formula using the definitions of `program`, which are
+/// compiled once each instead of being expanded at every use
+pub fn classify_in(program: &Program, expr: &Expr) -> Result<Analysis, EvalError> {
+    let vars = program.inputs(expr, &VarOrder::Sorted)?;
+
+    let (satisfying, falsifying) = if vars.len() > TABLE_LIMIT {
+        let not = Expr::Not(expr.clone().into());
+        (satisfy_in(program, expr)?, satisfy_in(program, &not)?)
+    } else {
+        let mut bdd = Bdd::with_order(&vars);
+        let f = program.compile(expr, &mut bdd)?;
+        let not_f = bdd.not(f);
+        (bdd.any_sat(f), bdd.any_sat(not_f))
+    };
+
+    let class = match (&satisfying, &falsifying) {
+        (Some(_), Some(_)) => Classification::Contingent,
+        (Some(_), None) => Classification::Tautology,
+        (None, _) => Classification::Contradiction,
+    };
+    Ok(Analysis {
+        class,
+        satisfying,
+        falsifying,
+    })
+}
+
+/// An assignment of the variables making `expr` true, with the definitions of `program`
+/// given to the solver once each
+fn satisfy_in(program: &Program, expr: &Expr) -> Result<Option<HashMap<String, bool>>, EvalError> {
+    let mut found = satisfy(&program.constrain(expr)?);
+    if let Some(w) = &mut found {
+        w.retain(|v, _| program.definition(v).is_none());
+    }
+    Ok(found)
+}
+
+/// Checks whether `a` and `b` have the same value under every assignment,
+/// returns an assignment under which they differ if they don't
+///
+/// Like [`classify`], this compares their diagrams in a [`Bdd`] if there are few enough
+/// variables and uses the SAT solver otherwise.
+pub fn find_difference(a: &Expr, b: &Expr) -> Result<Option<HashMap<String, bool>>, EvalError> {
+    find_difference_in(&Program::default(), a, b)
+}
+
+/// Like [`find_difference`] for formulas using the definitions of `program`
+pub fn find_difference_in(
+    program: &Program,
+    a: &Expr,
+    b: &Expr,
+) -> Result<Option<HashMap<String, bool>>, EvalError> {
+    let differ = Expr::Xor(a.clone().into(), b.clone().into());
+    let vars = program.inputs(&differ, &VarOrder::Sorted)?;
+
+    if vars.len() > TABLE_LIMIT {
+        return satisfy_in(program, &differ);
+    }
+    let mut bdd = Bdd::with_order(&vars);
+    let (f, g) = (program.compile(a, &mut bdd)?, program.compile(b, &mut bdd)?);
+    // equal functions are the same node
+    if f == g {
+        return Ok(None);
+    }
+    let differ = bdd.xor(f, g);
+    Ok(bdd.any_sat(differ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eval, parse_expr};
+
+    #[test]
+    fn differences() {
+        let (a, b) = (
+            parse_expr("a ^ b").unwrap(),
+            parse_expr("a <=> !b").unwrap(),
+        );
+        assert_eq!(find_difference(&a, &b).unwrap(), None);
+
+        let c = parse_expr("a | b").unwrap();
+        let w = find_difference(&a, &c).unwrap().unwrap();
+        assert_ne!(eval(&a, &w).unwrap(), eval(&c, &w).unwrap());
+    }
+
+    #[test]
+    fn classes() {
+        for (f, class) in [
+            ("a & b => a", Classification::Tautology),
+            ("a & !a", Classification::Contradiction),
+            ("a nor b", Classification::Contingent),
+        ] {
+            let expr = parse_expr(f).unwrap();
+            let analysis = classify(&expr).unwrap();
+            assert_eq!(analysis.class, class, "{f}");
+            if let Some(w) = &analysis.satisfying {
+                assert!(eval(&expr, w).unwrap());
+            }
+            if let Some(w) = &analysis.falsifying {
+                assert!(!eval(&expr, w).unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+
+use crate::{BigUint, EvalError, Expr};
+
+/// A node of a [`Bdd`], which stands for the boolean function rooted at it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub const FALSE: NodeId = NodeId(0);
+    pub const TRUE: NodeId = NodeId(1);
+
+    pub fn is_const(self) -> bool {
+        self.0 < 2
+    }
+
+    /// A number telling the node apart from the others in its store
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    var: usize,
+    low: NodeId,
+    high: NodeId,
+}
+
+/// A store of reduced ordered binary decision diagrams sharing their nodes
+///
+/// Nodes are hash-consed, so two functions are equal exactly when they are the same
+/// [`NodeId`]. Variables are tested in the order they were added, which can be
+/// changed with [`Bdd::sift`].
+#[derive(Debug, Clone)]
+pub struct Bdd {
+    nodes: Vec<Node>,
+    /// The node for every `(var, low, high)`, so that no node is built twice
+    unique: HashMap<(usize, NodeId, NodeId), NodeId>,
+    /// The results of `ite` calls
+    cache: HashMap<(NodeId, NodeId, NodeId), NodeId>,
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    /// The level of each variable, the lower the closer to the root
+    level: Vec<usize>,
+    /// The variable at each level
+    at_level: Vec<usize>,
+    /// The nodes testing each variable
+    by_var: Vec<Vec<NodeId>>,
+}
+
+impl Default for Bdd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bdd {
+    pub fn new() -> Self {
+        // the constants never have their variable looked at
+        let constant = Node {
+            var: usize::MAX,
+            low: NodeId::FALSE,
+            high: NodeId::FALSE,
+        };
+        Bdd {
+            nodes: vec![constant, constant],
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+            names: vec![],
+            index: HashMap::new(),
+            level: vec![],
+            at_level: vec![],
+            by_var: vec![],
+        }
+    }
+
+    /// A store with the variables `vars` tested in this order
+    pub fn with_order(vars: &[String]) -> Self {
+        let mut bdd = Bdd::new();
+        for v in vars {
+            bdd.var(v);
+        }
+        bdd
+    }
+
+    /// The variables from the root down
+    pub fn order(&self) -> Vec<String> {
+        self.at_level
+            .iter()
+            .map(|v| self.names[*v].clone())
+            .collect()
+    }
+
+    /// The function true when `name` is, adding the variable below the others if it is new
+    pub fn var(&mut self, name: &str) -> NodeId {
+        let var = match self.index.get(name) {
+            Some(v) => *v,
+            None => {
+                let v = self.names.len();
+                self.names.push(name.to_string());
+                self.index.insert(name.to_string(), v);
+                self.level.push(v);
+                self.at_level.push(v);
+                self.by_var.push(vec![]);
+                v
+            }
+        };
+        self.mk(var, NodeId::FALSE, NodeId::TRUE)
+    }
+
+    /// The level of the variable tested by `f`, the constants are below all of them
+    fn level_of(&self, f: NodeId) -> usize {
+        if f.is_const() {
+            self.names.len()
+        } else {
+            self.level[self.nodes[f.0].var]
+        }
+    }
+
+    fn mk(&mut self, var: usize, low: NodeId, high: NodeId) -> NodeId {
+        if low == high {
+            return low;
+        }
+        if let Some(n) = self.unique.get(&(var, low, high)) {
+            return *n;
+        }
+        let n = NodeId(self.nodes.len());
+        self.nodes.push(Node { var, low, high });
+        self.unique.insert((var, low, high), n);
+        self.by_var[var].push(n);
+        n
+    }
+
+    /// The two branches of `f` on `var`
+    fn cofactors(&self, f: NodeId, var: usize) -> (NodeId, NodeId) {
+        let node = self.nodes[f.0];
+        if !f.is_const() && node.var == var {
+            (node.low, node.high)
+        } else {
+            (f, f)
+        }
+    }
+
+    /// If `f` then `g` else `h`, which all the other operations are built on
+    pub fn ite(&mut self, f: NodeId, g: NodeId, h: NodeId) -> NodeId {
+        match (f, g, h) {
+            (NodeId::TRUE, _, _) => return g,
+            (NodeId::FALSE, _, _) => return h,
+            _ if g == h => return g,
+            (_, NodeId::TRUE, NodeId::FALSE) => return f,
+            _ => {}
+        }
+        if let Some(r) = self.cache.get(&(f, g, h)) {
+            return *r;
+        }
+        let top = self.level_of(f).min(self.level_of(g)).min(self.level_of(h));
+        let var = self.at_level[top];
+        let (f0, f1) = self.cofactors(f, var);
+        let (g0, g1) = self.cofactors(g, var);
+        let (h0, h1) = self.cofactors(h, var);
+        let low = self.ite(f0, g0, h0);
+        let high = self.ite(f1, g1, h1);
+        let r = self.mk(var, low, high);
+        self.cache.insert((f, g, h), r);
+        r
+    }
+
+    pub fn not(&mut self, f: NodeId) -> NodeId {
+        self.ite(f, NodeId::FALSE, NodeId::TRUE)
+    }
+
+    pub fn and(&mut self, f: NodeId, g: NodeId) -> NodeId {
+        self.ite(f, g, NodeId::FALSE)
+    }
+
+    pub fn or(&mut self, f: NodeId, g: NodeId) -> NodeId {
+        self.ite(f, NodeId::TRUE, g)
+    }
+
+    pub fn imply(&mut self, f: NodeId, g: NodeId) -> NodeId {
+        self.ite(f, g, NodeId::TRUE)
+    }
+
+    pub fn equiv(&mut self, f: NodeId, g: NodeId) -> NodeId {
+        let not_g = self.not(g);
+        self.ite(f, g, not_g)
+    }
+
+    pub fn xor(&mut self, f: NodeId, g: NodeId) -> NodeId {
+        let not_g = self.not(g);
+        self.ite(f, not_g, g)
+    }
+
+    /// Builds the diagram of `expr`, adding its new variables in the order they appear
+    pub fn compile(&mut self, expr: &Expr) -> NodeId {
+        self.compile_with(expr, &HashMap::new())
+    }
+
+    /// Like [`Bdd::compile`], with the variables named in `defs` standing for the
+    /// functions given there, like the definitions of a program
+    pub fn compile_with(&mut self, expr: &Expr, defs: &HashMap<String, NodeId>) -> NodeId {
+        match expr {
+            Expr::True => NodeId::TRUE,
+            Expr::False => NodeId::FALSE,
+            Expr::Term(t) => match defs.get(t) {
+                Some(f) => *f,
+                None => self.var(t),
+            },
+            Expr::Not(e) => {
+                let f = self.compile_with(e, defs);
+                self.not(f)
+            }
+            Expr::And(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                self.and(f, g)
+            }
+            Expr::Or(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                self.or(f, g)
+            }
+            Expr::Imply(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                self.imply(f, g)
+            }
+            Expr::Equiv(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                self.equiv(f, g)
+            }
+            Expr::Xor(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                self.xor(f, g)
+            }
+            Expr::Nand(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                let h = self.and(f, g);
+                self.not(h)
+            }
+            Expr::Nor(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                let h = self.or(f, g);
+                self.not(h)
+            }
+            Expr::Converse(l, r) => {
+                let (f, g) = (self.compile_with(l, defs), self.compile_with(r, defs));
+                self.imply(g, f)
+            }
+            Expr::Apply(m, args) => self.compile_with(&m.apply(args), defs),
+        }
+    }
+
+    /// `f` as a formula, `x & high | !x & low` for every node with the constants left out
+    ///
+    /// A node below several others is written out again under each of them, so this is
+    /// only small for small diagrams.
+    pub fn to_expr(&self, f: NodeId) -> Expr {
+        if f.is_const() {
+            return if f == NodeId::TRUE {
+                Expr::True
+            } else {
+                Expr::False
+            };
+        }
+        let node = self.nodes[f.0];
+        let x = Expr::Term(self.names[node.var].clone());
+        let branch = |x: Expr, g: NodeId| match g {
+            NodeId::TRUE => Some(x),
+            NodeId::FALSE => None,
+            g => Some(Expr::And(x.into(), self.to_expr(g).into())),
+        };
+        let high = branch(x.clone(), node.high);
+        let low = branch(Expr::Not(x.into()), node.low);
+        match (high, low) {
+            (Some(h), Some(l)) => Expr::Or(h.into(), l.into()),
+            (h, l) => h.or(l).expect("the branches of a node differ"),
+        }
+    }
+
+    /// The number of assignments of all the variables of the store making `f` true,
+    /// found by counting the paths to true
+    pub fn count(&self, f: NodeId) -> BigUint {
+        self.count_(f, &mut HashMap::new()) << self.level_of(f)
+    }
+
+    /// The number of assignments of the variables from the level of `f` down
+    fn count_(&self, f: NodeId, memo: &mut HashMap<NodeId, BigUint>) -> BigUint {
+        if f.is_const() {
+            return BigUint::from((f == NodeId::TRUE) as u64);
+        }
+        if let Some(c) = memo.get(&f) {
+            return c.clone();
+        }
+        let node = self.nodes[f.0];
+        let level = self.level_of(f);
+        // the variables skipped over by an edge can have any value
+        let low = self.count_(node.low, memo) << (self.level_of(node.low) - level - 1);
+        let high = self.count_(node.high, memo) << (self.level_of(node.high) - level - 1);
+        let c = low + high;
+        memo.insert(f, c.clone());
+        c
+    }
+
+    /// The probability of `f` being true when each variable is independently true with
+    /// the probability given in `weights`, or 1/2 if it is not there
+    pub fn probability(&self, f: NodeId, weights: &HashMap<String, f64>) -> f64 {
+        self.probability_(f, weights, &mut HashMap::new())
+    }
+
+    fn probability_(
+        &self,
+        f: NodeId,
+        weights: &HashMap<String, f64>,
+        memo: &mut HashMap<NodeId, f64>,
+    ) -> f64 {
+        if f.is_const() {
+            return (f == NodeId::TRUE) as u8 as f64;
+        }
+        if let Some(p) = memo.get(&f) {
+            return *p;
+        }
+        let node = self.nodes[f.0];
+        let w = weights.get(&self.names[node.var]).copied().unwrap_or(0.5);
+        let p = (1.0 - w) * self.probability_(node.low, weights, memo)
+            + w * self.probability_(node.high, weights, memo);
+        memo.insert(f, p);
+        p
+    }
+
+    /// The value of `f` under `values`, which only needs the variables on the path taken
+    pub fn eval(&self, f: NodeId, values: &HashMap<String, bool>) -> Result<bool, EvalError> {
+        let mut f = f;
+        while !f.is_const() {
+            let node = self.nodes[f.0];
+            let name = &self.names[node.var];
+            let value = values
+                .get(name)
+                .ok_or_else(|| EvalError::UndefinedVariable(name.clone()))?;
+            f = if *value { node.high } else { node.low };
+        }
+        Ok(f == NodeId::TRUE)
+    }
+
+    /// The variable tested by `f` with its low (false) and high (true) branches,
+    /// `None` for the constants
+    pub fn node(&self, f: NodeId) -> Option<(&str, NodeId, NodeId)> {
+        if f.is_const() {
+            return None;
+        }
+        let node = self.nodes[f.0];
+        Some((&self.names[node.var], node.low, node.high))
+    }
+
+    /// An assignment making `f` true, variables not on its path are false
+    pub fn any_sat(&self, f: NodeId) -> Option<HashMap<String, bool>> {
+        if f == NodeId::FALSE {
+            return None;
+        }
+        let mut assignment = self
+            .names
+            .iter()
+            .map(|v| (v.clone(), false))
+            .collect::<HashMap<_, _>>();
+        let mut f = f;
+        while !f.is_const() {
+            let node = self.nodes[f.0];
+            // every node other than false has a path to true
+            let value = node.low == NodeId::FALSE;
+            assignment.insert(self.names[node.var].clone(), value);
+            f = if value { node.high } else { node.low };
+        }
+        Some(assignment)
+    }
+
+    /// The number of nodes reachable from `roots`, not counting the constants
+    pub fn size(&self, roots: &[NodeId]) -> usize {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = roots.to_vec();
+        let mut size = 0;
+        while let Some(f) = stack.pop() {
+            if f.is_const() || seen[f.0] {
+                continue;
+            }
+            seen[f.0] = true;
+            size += 1;
+            stack.push(self.nodes[f.0].low);
+            stack.push(self.nodes[f.0].high);
+        }
+        size
+    }
+
+    /// Swaps the variables at levels `i` and `i + 1`, keeping every node the same function
+    fn swap(&mut self, i: usize) {
+        let (x, y) = (self.at_level[i], self.at_level[i + 1]);
+        for f in std::mem::take(&mut self.by_var[x]) {
+            let Node {
+                low: f0, high: f1, ..
+            } = self.nodes[f.0];
+            let (f00, f01) = self.cofactors(f0, y);
+            let (f10, f11) = self.cofactors(f1, y);
+            if (f00, f01, f10, f11) == (f0, f0, f1, f1) {
+                // does not depend on y, stays as it is
+                self.by_var[x].push(f);
+                continue;
+            }
+            let low = self.mk(x, f00, f10);
+            let high = self.mk(x, f01, f11);
+            self.unique.remove(&(x, f0, f1));
+            self.nodes[f.0] = Node { var: y, low, high };
+            self.unique.insert((y, low, high), f);
+            self.by_var[y].push(f);
+        }
+        self.at_level.swap(i, i + 1);
+        self.level[x] = i + 1;
+        self.level[y] = i;
+        self.cache.clear();
+    }
+
+    /// Reorders the variables to make the diagrams of `roots` smaller, by moving each
+    /// variable in turn to the level where they are the smallest
+    ///
+    /// The nodes not reachable from `roots` are dropped and `roots` are updated, any other
+    /// [`NodeId`] is invalid afterwards.
+    pub fn sift(&mut self, roots: &mut [NodeId]) {
+        self.collect_garbage(roots);
+        let levels = self.names.len();
+        let mut vars = (0..levels).collect::<Vec<_>>();
+        // the variables with the most nodes first
+        vars.sort_by_key(|v| std::cmp::Reverse(self.by_var[*v].len()));
+        for var in vars {
+            let mut best = (self.nodes.len(), self.level[var]);
+            // moving further is given up once the diagrams get much bigger
+            while self.level[var] + 1 < levels && self.nodes.len() <= 2 * best.0 {
+                self.swap_live(self.level[var], roots);
+                best = best.min((self.nodes.len(), self.level[var]));
+            }
+            while self.level[var] > 0 && self.nodes.len() <= 2 * best.0 {
+                self.swap_live(self.level[var] - 1, roots);
+                best = best.min((self.nodes.len(), self.level[var]));
+            }
+            while self.level[var] < best.1 {
+                self.swap_live(self.level[var], roots);
+            }
+        }
+    }
+
+    /// Swaps two levels and drops the nodes no longer used by `roots`, so that only the
+    /// live nodes are swapped and counted
+    fn swap_live(&mut self, i: usize, roots: &mut [NodeId]) {
+        self.swap(i);
+        self.collect_garbage(roots);
+    }
+
+    /// Drops the nodes not reachable from `roots`, renumbering the rest
+    fn collect_garbage(&mut self, roots: &mut [NodeId]) {
+        let mut renumbered = vec![None; self.nodes.len()];
+        renumbered[0] = Some(NodeId::FALSE);
+        renumbered[1] = Some(NodeId::TRUE);
+        let mut nodes = self.nodes[..2].to_vec();
+        for root in roots.iter_mut() {
+            self.renumber(*root, &mut renumbered, &mut nodes);
+            *root = renumbered[root.0].unwrap();
+        }
+
+        self.unique.clear();
+        self.cache.clear();
+        self.by_var.iter_mut().for_each(Vec::clear);
+        for (i, node) in nodes.iter().enumerate().skip(2) {
+            self.unique
+                .insert((node.var, node.low, node.high), NodeId(i));
+            self.by_var[node.var].push(NodeId(i));
+        }
+        self.nodes = nodes;
+    }
+
+    /// Copies `f` and the nodes below it into `nodes`, children before their parents
+    fn renumber(&self, f: NodeId, renumbered: &mut [Option<NodeId>], nodes: &mut Vec<Node>) {
+        if renumbered[f.0].is_some() {
+            return;
+        }
+        let node = self.nodes[f.0];
+        self.renumber(node.low, renumbered, nodes);
+        self.renumber(node.high, renumbered, nodes);
+        renumbered[f.0] = Some(NodeId(nodes.len()));
+        nodes.push(Node {
+            var: node.var,
+            low: renumbered[node.low.0].unwrap(),
+            high: renumbered[node.high.0].unwrap(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eval, make_table, parse_expr};
+
+    /// The pairs are far apart in this order, which makes the diagram of the first
+    /// formula grow exponentially
+    const ORDER: [&str; 6] = ["a1", "a2", "a3", "b1", "b2", "b3"];
+    const FORMULAS: [&str; 4] = [
+        "a1 & b1 | a2 & b2 | a3 & b3",
+        "(a1 <=> b3) ^ (a2 => !b1) nor a3",
+        "a1 & a3 | b1 & b2 | !a2 & !b3",
+        "(a1 | b2) & (a3 | b1) & !(a2 & b3) <= b2",
+    ];
+
+    fn build() -> (Bdd, Vec<Expr>, Vec<NodeId>) {
+        let mut bdd = Bdd::with_order(&ORDER.map(String::from));
+        let exprs = FORMULAS.map(|f| parse_expr(f).unwrap()).to_vec();
+        let roots = exprs.iter().map(|e| bdd.compile(e)).collect();
+        (bdd, exprs, roots)
+    }
+
+    /// Checks every root against its formula on every assignment
+    fn check(bdd: &Bdd, exprs: &[Expr], roots: &[NodeId]) {
+        for row in make_table(&ORDER.map(String::from)) {
+            for (e, f) in exprs.iter().zip(roots) {
+                assert_eq!(bdd.eval(*f, &row).unwrap(), eval(e, &row).unwrap(), "{e}");
+            }
+        }
+    }
+
+    #[test]
+    fn swap_keeps_functions() {
+        let (mut bdd, exprs, roots) = build();
+        for i in [0, 1, 2, 3, 4, 2, 0, 3, 1, 4, 0] {
+            bdd.swap(i);
+            check(&bdd, &exprs, &roots);
+        }
+        assert_ne!(bdd.order(), ORDER);
+    }
+
+    #[test]
+    fn sift_keeps_functions() {
+        let (mut bdd, exprs, mut roots) = build();
+        let before = bdd.size(&roots[..1]);
+        bdd.sift(&mut roots);
+        check(&bdd, &exprs, &roots);
+
+        let (mut bdd, _, mut roots) = build();
+        let first = &mut roots[..1];
+        bdd.sift(first);
+        assert!(bdd.size(first) < before);
+        check(&bdd, &exprs[..1], first);
+    }
+
+    #[test]
+    fn equal_functions_share_a_node() {
+        let mut bdd = Bdd::new();
+        let f = bdd.compile(&parse_expr("a => b & c").unwrap());
+        let g = bdd.compile(&parse_expr("!(a & !(b & c))").unwrap());
+        assert_eq!(f, g);
+        let not_f = bdd.not(f);
+        assert!(!bdd.eval(f, &bdd.any_sat(not_f).unwrap()).unwrap());
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+use std::ops::{Add, Mul, Shl};
+
+/// A non-negative integer of any size, enough for counting models
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    /// Base 2^32 digits from the least significant, without trailing zeros
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint::default()
+    }
+
+    pub fn one() -> Self {
+        BigUint::from(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    fn trim(mut self) -> Self {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        self
+    }
+
+    /// Divides in place by a small number, returning the remainder
+    fn div_rem_small(&mut self, d: u32) -> u32 {
+        let mut rem = 0u64;
+        for digit in self.digits.iter_mut().rev() {
+            let cur = rem << 32 | *digit as u64;
+            *digit = (cur / d as u64) as u32;
+            rem = cur % d as u64;
+        }
+        *self = std::mem::take(self).trim();
+        rem as u32
+    }
+
+    /// The closest floating point number, infinite if it is too big
+    pub fn to_f64(&self) -> f64 {
+        self.digits
+            .iter()
+            .rev()
+            .fold(0.0, |acc, d| acc * 4294967296.0 + *d as f64)
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> Self {
+        BigUint {
+            digits: vec![n as u32, (n >> 32) as u32],
+        }
+        .trim()
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let mut digits = Vec::with_capacity(self.digits.len().max(other.digits.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            let sum = carry
+                + *self.digits.get(i).unwrap_or(&0) as u64
+                + *other.digits.get(i).unwrap_or(&0) as u64;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        BigUint { digits }.trim()
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: BigUint) -> BigUint {
+        &self + &other
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.digits.iter().enumerate() {
+                let cur = digits[i + j] as u64 + *a as u64 * *b as u64 + carry;
+                digits[i + j] = cur as u32;
+                carry = cur >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigUint { digits }.trim()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        &self * &other
+    }
+}
+
+/// Multiplies by a power of two
+impl Shl<usize> for BigUint {
+    type Output = BigUint;
+
+    fn shl(self, bits: usize) -> BigUint {
+        if self.is_zero() {
+            return self;
+        }
+        let (words, bits) = (bits / 32, bits % 32);
+        let mut digits = vec![0; words];
+        let mut carry = 0u32;
+        for d in &self.digits {
+            let cur = (*d as u64) << bits | carry as u64;
+            digits.push(cur as u32);
+            carry = (cur >> 32) as u32;
+        }
+        digits.push(carry);
+        BigUint { digits }.trim()
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // nine decimal digits at a time, from the least significant
+        let mut n = self.clone();
+        let mut chunks = vec![];
+        while !n.is_zero() {
+            chunks.push(n.div_rem_small(1_000_000_000));
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+
+    fn big(n: u128) -> BigUint {
+        (BigUint::from((n >> 64) as u64) << 64) + BigUint::from(n as u64)
+    }
+
+    #[test]
+    fn agrees_with_u128() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let mut next = || {
+            let x = rng.next();
+            // small numbers too, so that carries and zeros are hit
+            x >> (x % 64)
+        };
+        for _ in 0..1000 {
+            let (a, b) = (next(), next());
+            let bits = (b % 64) as usize;
+            let (a, b) = (a as u128, b as u128);
+            assert_eq!(big(a).to_string(), a.to_string());
+            assert_eq!((big(a) + big(b)).to_string(), (a + b).to_string());
+            assert_eq!((big(a) * big(b)).to_string(), (a * b).to_string());
+            assert_eq!((big(a) << bits).to_string(), (a << bits).to_string());
+            assert_eq!(big(a) * big(b), big(a * b));
+        }
+    }
+
+    #[test]
+    fn large_numbers() {
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!((BigUint::zero() << 100).to_string(), "0");
+        assert_eq!(
+            (BigUint::one() << 200).to_string(),
+            "1606938044258990275541962092341162602522202993782792835301376"
+        );
+        let power =
+            |base: u64, exp: usize| (0..exp).fold(BigUint::one(), |n, _| n * BigUint::from(base));
+        assert_eq!(
+            power(3, 100).to_string(),
+            "515377520732011331036461129765621272702107522001"
+        );
+        // the chunks of nine digits below the first are padded with zeros
+        assert_eq!(power(10, 30).to_string(), format!("1{}", "0".repeat(30)));
+        assert_eq!((BigUint::one() << 1100).to_f64(), f64::INFINITY);
+        assert_eq!((BigUint::one() << 70).to_f64(), 2f64.powi(70));
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::sat::{Lit, Var};
+use crate::{get_vars, tseitin, Bdd, BigUint, Expr};
+
+/// The number of assignments of the variables of `expr` making it true
+///
+/// The formula is encoded with [`tseitin`], whose auxiliary variables are fixed by the
+/// others, and the models of the clauses are counted with [`count_cnf`]. Each variable
+/// missing from [`Encoding::vars`](crate::Encoding::vars) is free, doubling the count.
+pub fn count_models(expr: &Expr) -> BigUint {
+    let encoding = tseitin(expr);
+    let dropped = get_vars(expr)
+        .iter()
+        .filter(|v| !encoding.vars.contains_key(*v))
+        .count();
+    let mut clauses = encoding.clauses;
+    clauses.push(vec![encoding.root]);
+    count_cnf(encoding.num_vars, &clauses) << dropped
+}
+
+/// The probability of `expr` being true when each variable is independently true with
+/// the probability given in `weights`, or 1/2 if it is not there
+pub fn probability(expr: &Expr, weights: &HashMap<String, f64>) -> f64 {
+    let mut bdd = Bdd::new();
+    let f = bdd.compile(expr);
+    bdd.probability(f, weights)
+}
+
+/// The number of assignments of the variables `0..num_vars` satisfying every clause
+///
+/// This is DPLL going through every branch instead of stopping at the first model,
+/// counting independent parts of the clauses separately and remembering the counts
+/// of the parts it has seen.
+pub fn count_cnf(num_vars: usize, clauses: &[Vec<Lit>]) -> BigUint {
+    let mut clauses = clauses
+        .iter()
+        .filter_map(|c| {
+            let mut c = c.clone();
+            c.sort();
+            c.dedup();
+            // clauses with both polarities of a variable are always true
+            let tautology = c.windows(2).any(|w| w[0] == !w[1]);
+            (!tautology).then_some(c)
+        })
+        .collect::<Vec<_>>();
+    clauses.sort();
+    clauses.dedup();
+    let used = vars(&clauses).len();
+    let mut counter = Counter {
+        cache: HashMap::new(),
+    };
+    counter.count(clauses) << (num_vars - used)
+}
+
+fn vars(clauses: &[Vec<Lit>]) -> HashSet<Var> {
+    clauses.iter().flatten().map(|l| l.var()).collect()
+}
+
+/// Makes `lit` true, dropping the clauses it satisfies and its negation from the others
+fn assign(clauses: Vec<Vec<Lit>>, lit: Lit) -> Vec<Vec<Lit>> {
+    clauses
+        .into_iter()
+        .filter(|c| !c.contains(&lit))
+        .map(|mut c| {
+            c.retain(|l| *l != !lit);
+            c
+        })
+        .collect()
+}
+
+struct Counter {
+    /// The counts of the components seen so far
+    cache: HashMap<Vec<Vec<Lit>>, BigUint>,
+}
+
+impl Counter {
+    /// The number of assignments of the variables in `clauses` satisfying them
+    fn count(&mut self, clauses: Vec<Vec<Lit>>) -> BigUint {
+        let before = vars(&clauses).len();
+        // unit clauses leave no choice, so they do not change the count
+        let mut clauses = clauses;
+        let mut assigned = 0;
+        while let Some(unit) = clauses.iter().find(|c| c.len() <= 1) {
+            let Some(&lit) = unit.first() else {
+                return BigUint::zero();
+            };
+            clauses = assign(clauses, lit);
+            assigned += 1;
+        }
+        // the variables left out of every clause can have any value
+        let free = before - assigned - vars(&clauses).len();
+
+        let mut count = BigUint::one();
+        for component in components(clauses) {
+            count = count * self.component(component);
+            if count.is_zero() {
+                break;
+            }
+        }
+        count << free
+    }
+
+    fn component(&mut self, mut clauses: Vec<Vec<Lit>>) -> BigUint {
+        for c in &mut clauses {
+            c.sort();
+        }
+        clauses.sort();
+        if let Some(count) = self.cache.get(&clauses) {
+            return count.clone();
+        }
+
+        // branch on the variable in the most clauses
+        let mut occurrences = HashMap::new();
+        for l in clauses.iter().flatten() {
+            *occurrences.entry(l.var()).or_insert(0) += 1;
+        }
+        let (&var, _) = occurrences.iter().max_by_key(|(v, n)| (**n, **v)).unwrap();
+        let before = occurrences.len();
+        let mut count = BigUint::zero();
+        for value in [false, true] {
+            let rest = assign(clauses.clone(), Lit::new(var, value));
+            let vanished = before - 1 - vars(&rest).len();
+            count = count + (self.count(rest) << vanished);
+        }
+        self.cache.insert(clauses, count.clone());
+        count
+    }
+}
+
+/// Splits `clauses` into groups sharing no variables
+fn components(clauses: Vec<Vec<Lit>>) -> Vec<Vec<Vec<Lit>>> {
+    // union-find over the variables, keyed by the first clause seen with each
+    let mut parent = (0..clauses.len()).collect::<Vec<_>>();
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut owner = HashMap::new();
+    for (i, c) in clauses.iter().enumerate() {
+        for l in c {
+            let j = *owner.entry(l.var()).or_insert(i);
+            let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+            parent[a] = b;
+        }
+    }
+    let mut groups: HashMap<usize, Vec<Vec<Lit>>> = HashMap::new();
+    for (i, c) in clauses.into_iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(c);
+    }
+    groups.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+    use crate::{eval, make_table, parse_program};
+
+    #[test]
+    fn agrees_with_truth_table() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..300 {
+            // chains of five connectives over `a` to `e`
+            let expr = rng.formula(5, 6);
+            let mut vars = get_vars(&expr).into_iter().collect::<Vec<_>>();
+            vars.sort();
+            let expected = make_table(&vars)
+                .iter()
+                .filter(|row| eval(&expr, row).unwrap())
+                .count();
+            assert_eq!(
+                count_models(&expr),
+                BigUint::from(expected as u64),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn counts_clauses() {
+        let lit = |l: i32| Lit::new(l.unsigned_abs() as usize - 1, l > 0);
+        let cnf = |clauses: &[&[i32]]| {
+            clauses
+                .iter()
+                .map(|c| c.iter().map(|l| lit(*l)).collect())
+                .collect::<Vec<_>>()
+        };
+        // no clauses, every assignment counts
+        assert_eq!(count_cnf(70, &[]), BigUint::one() << 70);
+        assert_eq!(count_cnf(3, &cnf(&[&[]])), BigUint::zero());
+        // tautologies and duplicates are dropped
+        assert_eq!(
+            count_cnf(2, &cnf(&[&[1, -1], &[2], &[2, 2]])).to_string(),
+            "2"
+        );
+        // two independent parts, 3 * 3 models of their variables times 2 for the free one
+        let parts = cnf(&[&[1, 2], &[3, 4]]);
+        assert_eq!(count_cnf(5, &parts).to_string(), "18");
+        assert_eq!(count_cnf(2, &cnf(&[&[1], &[-1]])), BigUint::zero());
+    }
+
+    #[test]
+    fn unused_parameters_count() {
+        let program = parse_program("m(x, y) = x; f = m(a, b);").unwrap();
+        let f = program.definition("f").unwrap();
+        assert_eq!(count_models(f).to_string(), "2");
+    }
+}
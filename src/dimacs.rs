@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::normal::build;
+use crate::{cnf_clauses, get_vars, parse_expr, EvalError, Expr, Literal};
+
+/// Splits a line into words with their byte offsets in it
+fn words(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.split_whitespace()
+        .map(move |w| (w.as_ptr() as usize - line.as_ptr() as usize, w))
+}
+
+fn error(span: Range<usize>, message: impl Into<String>) -> EvalError {
+    EvalError::Parse {
+        span,
+        message: message.into(),
+    }
+}
+
+/// Whether `name` parses back as the variable itself, which reserved words like `true`
+/// and names like `a-b` do not
+fn is_name(name: &str) -> bool {
+    parse_expr(name).is_ok_and(|e| e == Expr::Term(name.to_string()))
+}
+
+/// Reads a DIMACS CNF problem as a conjunction of clauses
+///
+/// Variables are named by a block of comments like `c 1 name` right after a `c variables`
+/// comment before the header, as written by [`write_dimacs`]. The others, and the ones
+/// whose name is not a valid variable, are named `x<number>`. Other comments are ignored
+/// even if they look like names, like `c 3 clauses`. Two variables with the same name,
+/// given or not, are an error.
+pub fn read_dimacs(input: &str) -> Result<Expr, EvalError> {
+    // the names of the variables, with the spans of the names
+    let mut names: HashMap<i64, (String, Range<usize>)> = HashMap::new();
+    // whether the line is in the block of names
+    let mut naming = false;
+    // the declared numbers of variables and clauses, with the span of the header
+    let mut header: Option<(i64, usize, Range<usize>)> = None;
+    let mut clauses: Vec<Vec<i64>> = vec![];
+    let mut clause = vec![];
+
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let tokens = words(line)
+            .map(|(i, w)| (start + i..start + i + w.len(), w))
+            .collect::<Vec<_>>();
+        let in_names = std::mem::take(&mut naming);
+        match tokens.as_slice() {
+            [] => naming = in_names,
+            [(_, "c"), (_, "variables")] if header.is_none() => naming = true,
+            [(_, "c"), (_, n), (span, name)]
+                if in_names && n.parse::<usize>().is_ok_and(|n| n > 0) =>
+            {
+                naming = true;
+                let n = n.parse().unwrap();
+                if names.contains_key(&n) {
+                    return Err(error(span.clone(), format!("variable {n} is named twice")));
+                }
+                if let Some((m, _)) = names.iter().find(|(_, (other, _))| other == name) {
+                    return Err(error(
+                        span.clone(),
+                        format!("`{name}` already names variable {m}"),
+                    ));
+                }
+                if is_name(name) {
+                    names.insert(n, (name.to_string(), span.clone()));
+                }
+            }
+            [(_, c), ..] if c.starts_with('c') => {}
+            // some benchmark files end with `%`
+            [(_, "%"), ..] => break,
+            [(p, w), ..] if w.starts_with('p') => {
+                let span = p.start..tokens.last().unwrap().0.end;
+                if header.is_some() {
+                    return Err(error(span, "duplicate `p cnf` header"));
+                }
+                let [(_, "p"), (_, "cnf"), (_, vars), (_, count)] = tokens.as_slice() else {
+                    return Err(error(span, "expected `p cnf <variables> <clauses>`"));
+                };
+                let (Ok(vars), Ok(count)) = (vars.parse(), count.parse()) else {
+                    return Err(error(span, "expected `p cnf <variables> <clauses>`"));
+                };
+                header = Some((vars, count, span));
+            }
+            _ => {
+                for (span, w) in tokens {
+                    let Some((vars, _, _)) = header else {
+                        return Err(error(span, "expected `p cnf` header before the clauses"));
+                    };
+                    let Ok(lit) = w.parse::<i64>() else {
+                        return Err(error(span, "expected a literal"));
+                    };
+                    if lit == 0 {
+                        clauses.push(std::mem::take(&mut clause));
+                    } else if lit.abs() > vars {
+                        return Err(error(
+                            span,
+                            format!("variable {} is not declared in the header", lit.abs()),
+                        ));
+                    } else {
+                        clause.push(lit);
+                    }
+                }
+            }
+        }
+    }
+
+    let Some((vars, count, span)) = header else {
+        if input.trim().is_empty() {
+            return Err(EvalError::EmptyInput);
+        }
+        return Err(error(0..0, "expected `p cnf` header"));
+    };
+    // the final 0 is often left out
+    if !clause.is_empty() {
+        clauses.push(clause);
+    }
+    if clauses.len() != count {
+        return Err(error(
+            span,
+            format!("header declares {count} clauses, found {}", clauses.len()),
+        ));
+    }
+
+    for (&n, (name, span)) in &names {
+        if n > vars {
+            return Err(error(
+                span.clone(),
+                format!("variable {n} is not declared in the header"),
+            ));
+        }
+        // the name of another variable without one
+        let fallback = name.strip_prefix('x').and_then(|m| m.parse::<i64>().ok());
+        if let Some(m) = fallback.filter(|m| *name == format!("x{m}") && *m != n) {
+            if m <= vars && !names.contains_key(&m) {
+                return Err(error(
+                    span.clone(),
+                    format!("`{name}` is the name of variable {m}, which has no other"),
+                ));
+            }
+        }
+    }
+
+    let clauses = clauses
+        .into_iter()
+        .map(|c| {
+            c.into_iter()
+                .map(|lit| {
+                    let name = names
+                        .get(&lit.abs())
+                        .map(|(name, _)| name.clone())
+                        .unwrap_or_else(|| format!("x{}", lit.abs()));
+                    (name, lit > 0)
+                })
+                .collect::<Vec<Literal>>()
+        })
+        .collect::<Vec<_>>();
+    Ok(build(&clauses, true))
+}
+
+/// Writes `expr` as a DIMACS CNF problem, converting it with [`cnf_clauses`]
+///
+/// Variables are numbered in sorted order and listed in comments before the header.
+pub fn write_dimacs(expr: &Expr) -> String {
+    let mut vars = get_vars(expr).into_iter().collect::<Vec<_>>();
+    vars.sort();
+    let numbers = vars
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v, i + 1))
+        .collect::<HashMap<_, _>>();
+    let clauses = cnf_clauses(expr);
+
+    let mut out = String::from("c variables\n");
+    for (i, v) in vars.iter().enumerate() {
+        out += &format!("c {} {v}\n", i + 1);
+    }
+    out += &format!("p cnf {} {}\n", vars.len(), clauses.len());
+    for clause in clauses {
+        for (v, positive) in clause {
+            let sign = if positive { "" } else { "-" };
+            out += &format!("{sign}{} ", numbers[&v]);
+        }
+        out += "0\n";
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_names_are_numbered() {
+        let input = "c variables\nc 1 true\nc 2 a-b\nc 3 a[3]\np cnf 3 1\n1 -2 3 0\n";
+        let expr = read_dimacs(input).unwrap();
+        assert_eq!(expr.to_string(), "x1 | !x2 | a[3]");
+        assert_eq!(parse_expr(&expr.to_string()).unwrap(), expr);
+    }
+
+    #[test]
+    fn round_trip() {
+        for f in [
+            "a & (b | !c) => d[2]",
+            "x2 ^ a <=> x1",
+            "!(p nand q) | r nor x10",
+        ] {
+            let expr = parse_expr(f).unwrap();
+            let read = read_dimacs(&write_dimacs(&expr)).unwrap();
+            assert_eq!(read, build(&cnf_clauses(&expr), true), "{f}");
+        }
+    }
+
+    /// The span and message of the error reading `input`
+    fn error(input: &str) -> (String, String) {
+        match read_dimacs(input) {
+            Err(EvalError::Parse { span, message }) => (input[span].to_string(), message),
+            other => panic!("{input}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn names_are_not_merged() {
+        let (at, message) = error("c variables\nc 1 a\nc 2 a\np cnf 2 1\n1 2 0\n");
+        assert_eq!(
+            (at.as_str(), message.as_str()),
+            ("a", "`a` already names variable 1")
+        );
+        let (at, message) = error("c variables\nc 1 a\nc 1 b\np cnf 2 1\n1 2 0\n");
+        assert_eq!(
+            (at.as_str(), message.as_str()),
+            ("b", "variable 1 is named twice")
+        );
+        // `x2` is what variable 2 is called without a name
+        let (at, message) = error("c variables\nc 1 x2\np cnf 2 1\n1 2 0\n");
+        assert_eq!(at, "x2");
+        assert_eq!(
+            message,
+            "`x2` is the name of variable 2, which has no other"
+        );
+        // unless it has one
+        let input = "c variables\nc 1 x2\nc 2 x1\np cnf 2 1\n1 -2 0\n";
+        assert_eq!(read_dimacs(input).unwrap().to_string(), "x2 | !x1");
+        let input = "c variables\nc 3 x02\np cnf 3 1\n2 3 0\n";
+        assert_eq!(read_dimacs(input).unwrap().to_string(), "x2 | x02");
+    }
+
+    #[test]
+    fn other_comments_are_not_names() {
+        let input = "c 3 clauses\nc variables\nc 1 a\nc\nc 2 b\np cnf 3 1\n1 2 3 0\n";
+        assert_eq!(read_dimacs(input).unwrap().to_string(), "a | x2 | x3");
+        let input = "p cnf 3 1\nc variables\nc 2 b\n1 2 3 0\n";
+        assert_eq!(read_dimacs(input).unwrap().to_string(), "x1 | x2 | x3");
+    }
+}
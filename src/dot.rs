@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+use crate::{Bdd, Expr, NodeId};
+
+/// Quotes `s` as a DOT string
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Renders the syntax tree of `expr` as a Graphviz graph named `name`, with one node
+/// per operator and variable
+pub fn expr_dot(name: &str, expr: &Expr) -> String {
+    let mut lines = vec![format!("digraph {} {{", quote(name))];
+    let mut count = 0;
+    expr_nodes(expr, &mut count, &mut lines);
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+/// Adds the nodes of `expr` numbered from `count`, returns the number of its root
+fn expr_nodes(expr: &Expr, count: &mut usize, lines: &mut Vec<String>) -> usize {
+    let id = *count;
+    *count += 1;
+    let (label, children) = match expr {
+        Expr::True => ("true", vec![]),
+        Expr::False => ("false", vec![]),
+        Expr::Term(t) => (t.as_str(), vec![]),
+        Expr::Not(e) => ("!", vec![&**e]),
+        Expr::And(l, r) => ("&", vec![&**l, r]),
+        Expr::Or(l, r) => ("|", vec![&**l, r]),
+        Expr::Imply(l, r) => ("=>", vec![&**l, r]),
+        Expr::Equiv(l, r) => ("<=>", vec![&**l, r]),
+        Expr::Xor(l, r) => ("^", vec![&**l, r]),
+        Expr::Nand(l, r) => ("nand", vec![&**l, r]),
+        Expr::Nor(l, r) => ("nor", vec![&**l, r]),
+        Expr::Converse(l, r) => ("<=", vec![&**l, r]),
+        Expr::Apply(m, args) => (m.name.as_str(), args.iter().collect()),
+    };
+    let shape = if children.is_empty() {
+        "box"
+    } else {
+        "ellipse"
+    };
+    lines.push(format!("  n{id} [label={}, shape={shape}];", quote(label)));
+    for child in children {
+        let c = expr_nodes(child, count, lines);
+        lines.push(format!("  n{id} -> n{c};"));
+    }
+    id
+}
+
+/// Renders the diagrams of `roots` in `bdd` as a Graphviz graph named `name`, with
+/// dashed edges for the low (false) branches and solid ones for the high (true) branches
+///
+/// Each root is pointed to by a node with its name, nodes testing the same variable are
+/// drawn on the same rank.
+pub fn bdd_dot(name: &str, bdd: &Bdd, roots: &[(String, NodeId)]) -> String {
+    let mut lines = vec![
+        format!("digraph {} {{", quote(name)),
+        "  node [shape=circle];".to_string(),
+    ];
+    let mut seen = HashSet::new();
+    let mut stack = vec![];
+    for (i, (root_name, f)) in roots.iter().enumerate() {
+        lines.push(format!(
+            "  r{i} [label={}, shape=plaintext];",
+            quote(root_name)
+        ));
+        lines.push(format!("  r{i} -> {};", node_id(*f)));
+        stack.push(*f);
+    }
+
+    let order = bdd.order();
+    let mut ranks = vec![vec![]; order.len()];
+    while let Some(f) = stack.pop() {
+        if !seen.insert(f) {
+            continue;
+        }
+        let Some((var, low, high)) = bdd.node(f) else {
+            let label = if f == NodeId::TRUE { "1" } else { "0" };
+            lines.push(format!("  {} [label=\"{label}\", shape=box];", node_id(f)));
+            continue;
+        };
+        lines.push(format!("  {} [label={}];", node_id(f), quote(var)));
+        lines.push(format!(
+            "  {} -> {} [style=dashed];",
+            node_id(f),
+            node_id(low)
+        ));
+        lines.push(format!("  {} -> {};", node_id(f), node_id(high)));
+        ranks[order.iter().position(|v| v == var).unwrap()].push(node_id(f));
+        stack.push(high);
+        stack.push(low);
+    }
+    for rank in ranks.iter().filter(|r| r.len() > 1) {
+        lines.push(format!("  {{ rank=same; {}; }}", rank.join("; ")));
+    }
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+fn node_id(f: NodeId) -> String {
+    match f {
+        NodeId::FALSE => "zero".to_string(),
+        NodeId::TRUE => "one".to_string(),
+        _ => format!("n{}", f.index()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_expr;
+
+    #[test]
+    fn syntax_trees() {
+        // every use of a variable is a node of its own
+        let expr = parse_expr("!x[1] & (b | x[1])").unwrap();
+        assert_eq!(
+            expr_dot("say \"hi\"", &expr),
+            r#"digraph "say \"hi\"" {
+  n0 [label="&", shape=ellipse];
+  n1 [label="!", shape=ellipse];
+  n2 [label="x[1]", shape=box];
+  n1 -> n2;
+  n0 -> n1;
+  n3 [label="|", shape=ellipse];
+  n4 [label="b", shape=box];
+  n3 -> n4;
+  n5 [label="x[1]", shape=box];
+  n3 -> n5;
+  n0 -> n3;
+}"#
+        );
+        assert_eq!(quote(r#"a\"b"#), r#""a\\\"b""#);
+    }
+
+    #[test]
+    fn diagrams_share_nodes() {
+        let mut bdd = Bdd::new();
+        let f = bdd.compile(&parse_expr("a & b | c").unwrap());
+        let g = bdd.compile(&parse_expr("b | c").unwrap());
+        // `g` is the high branch of `f`, and `c` is below both
+        assert_eq!(
+            bdd_dot("f", &bdd, &[("f".into(), f), ("g[1]".into(), g)]),
+            r#"digraph "f" {
+  node [shape=circle];
+  r0 [label="f", shape=plaintext];
+  r0 -> n7;
+  r1 [label="g[1]", shape=plaintext];
+  r1 -> n6;
+  n6 [label="b"];
+  n6 -> n5 [style=dashed];
+  n6 -> one;
+  n5 [label="c"];
+  n5 -> zero [style=dashed];
+  n5 -> one;
+  zero [label="0", shape=box];
+  one [label="1", shape=box];
+  n7 [label="a"];
+  n7 -> n5 [style=dashed];
+  n7 -> n6;
+}"#
+        );
+    }
+
+    #[test]
+    fn diagram_ranks() {
+        let mut bdd = Bdd::new();
+        let f = bdd.compile(&parse_expr("x[1] ^ y").unwrap());
+        assert_eq!(
+            bdd_dot("x", &bdd, &[("f".into(), f)]),
+            r#"digraph "x" {
+  node [shape=circle];
+  r0 [label="f", shape=plaintext];
+  r0 -> n5;
+  n5 [label="x[1]"];
+  n5 -> n3 [style=dashed];
+  n5 -> n4;
+  n3 [label="y"];
+  n3 -> zero [style=dashed];
+  n3 -> one;
+  zero [label="0", shape=box];
+  one [label="1", shape=box];
+  n4 [label="y"];
+  n4 -> one [style=dashed];
+  n4 -> zero;
+  { rank=same; n3; n4; }
+}"#
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+use std::ops::Range;
+
+/// Everything that can go wrong while reading or evaluating formulas
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// The input could not be parsed, `span` is in bytes
+    Parse { span: Range<usize>, message: String },
+    /// There was nothing to parse in the input
+    EmptyInput,
+    /// A variable without an assigned value
+    UndefinedVariable(String),
+    /// A name that does not refer to any definition
+    UnknownDefinition(String),
+    /// A definition using itself, with the definitions leading back to it
+    RecursiveDefinition(Vec<String>),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Parse { span, message } => {
+                write!(f, "{message} (at {}..{})", span.start, span.end)
+            }
+            EvalError::EmptyInput => write!(f, "nothing to parse"),
+            EvalError::UndefinedVariable(v) => write!(f, "variable \"{v}\" has no value"),
+            EvalError::UnknownDefinition(name) => write!(f, "no definition named \"{name}\""),
+            EvalError::RecursiveDefinition(cycle) => {
+                write!(f, "\"{}\" is defined in terms of itself", cycle[0])?;
+                if cycle.len() > 2 {
+                    write!(f, " ({})", cycle.join(" -> "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl EvalError {
+    /// Formats the error for the user, showing where in `source` it happened if possible
+    pub fn report(&self, source: &str) -> String {
+        let EvalError::Parse { span, message } = self else {
+            return format!("error: {self}");
+        };
+        let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[span.start..]
+            .find('\n')
+            .map_or(source.len(), |i| span.start + i);
+        let line = &source[line_start..line_end];
+        let line_num = source[..span.start].matches('\n').count() + 1;
+        let col = source[line_start..span.start].chars().count();
+        // spans reaching past the line are cut off at its end
+        let width = source[span.start..span.end.min(line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        let pad = " ".repeat(line_num.to_string().len());
+        format!(
+            "error: {message}\n{pad}--> {line_num}:{col}\n{pad} |\n{line_num} | {line}\n{pad} | {}{}",
+            " ".repeat(col),
+            "^".repeat(width),
+            col = col + 1,
+        )
+    }
+}
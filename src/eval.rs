@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{EvalError, Expr, Program};
+
+/// Above this many variables a truth table is too big to be useful,
+/// and the analyses use the SAT solver instead
+pub const TABLE_LIMIT: usize = 16;
+
+/// Evaluates `expr`, `vars` has to contain a value for every variable in it
+pub fn eval(expr: &Expr, vars: &HashMap<String, bool>) -> Result<bool, EvalError> {
+    let symbols = get_vars(expr);
+    for s in symbols {
+        if !vars.contains_key(&s) {
+            return Err(EvalError::UndefinedVariable(s));
+        }
+    }
+    eval_(expr, vars)
+}
+fn eval_(expr: &Expr, vars: &HashMap<String, bool>) -> Result<bool, EvalError> {
+    // REMINDER: || and && SHORT-CIRCUIT
+    // which means that if there is an unknown symbol, it gets ignored
+    Ok(match expr {
+        Expr::True => true,
+        Expr::False => false,
+        Expr::Term(t) => {
+            let Some(val) = vars.get(t) else {
+                return Err(EvalError::UndefinedVariable(t.clone()));
+            };
+            *val
+        }
+        Expr::Not(e) => !eval_(e, vars)?,
+        Expr::Or(l, r) => eval_(l, vars)? | eval_(r, vars)?,
+        Expr::And(l, r) => eval_(l, vars)? & eval_(r, vars)?,
+        Expr::Imply(l, r) => !eval_(l, vars)? | eval_(r, vars)?,
+        Expr::Equiv(l, r) => !(eval_(l, vars)? ^ eval_(r, vars)?),
+        Expr::Xor(l, r) => eval_(l, vars)? ^ eval_(r, vars)?,
+        Expr::Nand(l, r) => !(eval_(l, vars)? & eval_(r, vars)?),
+        Expr::Nor(l, r) => !(eval_(l, vars)? | eval_(r, vars)?),
+        Expr::Converse(l, r) => eval_(l, vars)? | !eval_(r, vars)?,
+        // the body only sees the values of the arguments
+        Expr::Apply(m, args) => {
+            let mut values = HashMap::new();
+            for (param, arg) in m.params.iter().zip(args) {
+                values.insert(param.clone(), eval_(arg, vars)?);
+            }
+            eval_(&m.body, &values)?
+        }
+    })
+}
+
+/// Collects the variables used in `expr`
+pub fn get_vars(expr: &Expr) -> HashSet<String> {
+    let mut set = HashSet::new();
+    set.extend(get_vars_(expr));
+    set
+}
+// in order of appearance, with duplicates
+pub(crate) fn get_vars_(expr: &Expr) -> Vec<String> {
+    match expr {
+        Expr::True => vec![],
+        Expr::False => vec![],
+        Expr::Term(t) => vec![t.clone()],
+        Expr::Not(e) => get_vars_(e),
+        Expr::Or(l, r) => {
+            let mut l1 = get_vars_(l);
+            let mut r1 = get_vars_(r);
+            l1.append(&mut r1);
+            l1
+        }
+        Expr::And(l, r) => {
+            let mut l1 = get_vars_(l);
+            let mut r1 = get_vars_(r);
+            l1.append(&mut r1);
+            l1
+        }
+        Expr::Imply(l, r) => {
+            let mut l1 = get_vars_(l);
+            let mut r1 = get_vars_(r);
+            l1.append(&mut r1);
+            l1
+        }
+        Expr::Equiv(l, r)
+        | Expr::Xor(l, r)
+        | Expr::Nand(l, r)
+        | Expr::Nor(l, r)
+        | Expr::Converse(l, r) => {
+            let mut l1 = get_vars_(l);
+            let mut r1 = get_vars_(r);
+            l1.append(&mut r1);
+            l1
+        }
+        Expr::Apply(_, args) => args.iter().flat_map(get_vars_).collect(),
+    }
+}
+
+/// How the variables, and so the columns of the table, are ordered
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum VarOrder {
+    #[default]
+    Sorted,
+    /// In the order they first appear in the definitions
+    FirstAppearance,
+    /// The given variables first, then the rest sorted
+    Custom(Vec<String>),
+}
+
+impl fmt::Display for VarOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VarOrder::Sorted => write!(f, "sorted"),
+            VarOrder::FirstAppearance => write!(f, "appearance"),
+            VarOrder::Custom(vars) => write!(f, "{}", vars.join(" ")),
+        }
+    }
+}
+
+/// Lists the variables used in `exprs` in the given order
+pub fn order_vars(exprs: &[Expr], order: &VarOrder) -> Vec<String> {
+    let mut vars = vec![];
+    for v in exprs.iter().flat_map(get_vars_) {
+        if !vars.contains(&v) {
+            vars.push(v);
+        }
+    }
+    match order {
+        VarOrder::FirstAppearance => vars,
+        VarOrder::Sorted => {
+            vars.sort();
+            vars
+        }
+        VarOrder::Custom(first) => {
+            // the order is kept between inputs, so it may name variables that are not used
+            let mut ordered = first
+                .iter()
+                .filter(|v| vars.contains(v))
+                .cloned()
+                .collect::<Vec<_>>();
+            let mut rest = vars
+                .into_iter()
+                .filter(|v| !first.contains(v))
+                .collect::<Vec<_>>();
+            rest.sort();
+            ordered.append(&mut rest);
+            ordered
+        }
+    }
+}
+
+/// Lists all assignments of `vars`, counting up in binary
+pub fn make_table(vars: &[String]) -> Vec<HashMap<String, bool>> {
+    // the first variable changes the slowest, so the rows count up in binary
+    // with no variables at all there is still the one (empty) row
+    let mut tables: Vec<HashMap<String, bool>> = vec![HashMap::new()];
+
+    for v in vars {
+        let mut ls2 = vec![];
+        for table in tables {
+            let mut new_table = table.clone();
+            new_table.insert(v.clone(), false);
+            ls2.push(new_table);
+
+            let mut new_table2 = table.clone();
+            new_table2.insert(v.clone(), true);
+
+            ls2.push(new_table2);
+        }
+        tables = ls2;
+    }
+    tables
+}
+
+/// A value for each variable
+pub type Assignment = HashMap<String, bool>;
+
+/// The values of a list of definitions under every assignment of their variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruthTable {
+    /// The variables, in the order of the columns
+    pub vars: Vec<String>,
+    /// The names of the definitions
+    pub names: Vec<String>,
+    /// Every assignment of `vars` with the value of each definition under it,
+    /// `None` where it is a don't-care
+    pub rows: Vec<(Assignment, Vec<Option<bool>>)>,
+}
+
+/// Evaluates the definitions of `program` under every assignment of their variables,
+/// ordered by `order`
+///
+/// Definitions used by others are evaluated first and their values used in place of
+/// their names. If `inline` is set they get no column of their own.
+pub fn truth_table(
+    program: &Program,
+    order: &VarOrder,
+    inline: bool,
+) -> Result<TruthTable, EvalError> {
+    let dependencies = program.dependency_order()?;
+    let vars = program.vars(order);
+    let (names, dont_cares): (Vec<String>, Vec<Option<&Expr>>) = program
+        .definitions
+        .iter()
+        .filter(|(n, _)| !inline || !program.is_used(n))
+        .map(|(n, _)| (n.clone(), program.dont_care(n)))
+        .unzip();
+    let rows = make_table(&vars)
+        .into_iter()
+        .map(|row| {
+            let mut values = row.clone();
+            for &i in &dependencies {
+                let (name, expr) = &program.definitions[i];
+                values.insert(name.clone(), eval(expr, &values)?);
+            }
+            let results = names
+                .iter()
+                .zip(&dont_cares)
+                .map(|(n, dc)| match dc {
+                    Some(dc) if eval(dc, &values)? => Ok(None),
+                    _ => Ok(Some(values[n])),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((row, results))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(TruthTable { vars, names, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+
+    /// The results of `table` as a string per definition, one character per row
+    fn columns(table: &TruthTable) -> Vec<String> {
+        (0..table.names.len())
+            .map(|i| {
+                table
+                    .rows
+                    .iter()
+                    .map(|(_, results)| match results[i] {
+                        Some(true) => '1',
+                        Some(false) => '0',
+                        None => '-',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn definitions_use_each_other() {
+        // `g` comes first but needs `f`, which needs `h`
+        let program = parse_program("g = f | c; f = a & h; h = !b;").unwrap();
+        let order = program.dependency_order().unwrap();
+        let names = order
+            .iter()
+            .map(|&i| program.definitions[i].0.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["h", "f", "g"]);
+
+        // the definitions used by others are no variables
+        let table = truth_table(&program, &VarOrder::Sorted, false).unwrap();
+        assert_eq!(table.vars, ["a", "b", "c"]);
+        assert_eq!(table.names, ["g", "f", "h"]);
+        assert_eq!(columns(&table), ["01011101", "00001100", "11001100"]);
+
+        // inlined, only `g` is left
+        let table = truth_table(&program, &VarOrder::Sorted, true).unwrap();
+        assert_eq!(table.vars, ["a", "b", "c"]);
+        assert_eq!(table.names, ["g"]);
+        assert_eq!(columns(&table), ["01011101"]);
+    }
+
+    #[test]
+    fn dont_cares() {
+        let program = parse_program("f = a ^ b; dc f = a & b; g = f & c;").unwrap();
+        let table = truth_table(&program, &VarOrder::Sorted, false).unwrap();
+        assert_eq!(table.names, ["f", "g"]);
+        // `f` is still computed where it does not matter, for `g` to use
+        assert_eq!(columns(&table), ["001111--", "00010100"]);
+    }
+}
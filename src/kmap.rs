@@ -0,0 +1,236 @@
+use crate::{eval, make_table, minimize, minimize_pos, EvalError, Expr, Implicant};
+
+/// The groups marked on a map by [`kmap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Groups {
+    /// No groups, only the values
+    Plain,
+    /// The products of the minimal sum of products, covering the ones
+    Products,
+    /// The sums of the minimal product of sums, covering the zeros
+    Sums,
+}
+
+/// The values of `bits` bits in Gray code order, so neighbours differ in a single bit
+fn gray(bits: usize) -> Vec<usize> {
+    (0..1 << bits).map(|i| i ^ (i >> 1)).collect()
+}
+
+fn bits(value: usize, width: usize) -> String {
+    format!("{value:0width$b}")
+}
+
+/// Renders the Karnaugh map of `expr` over `vars`, marking the rows covered by each
+/// of `groups` with a letter, `A` for the first one
+///
+/// Rows where `dont_care` is true are shown as `X`.
+///
+/// Maps of 5 and 6 variables are drawn as two or four maps of the last 4 variables,
+/// one for each value of the first ones, placed so that neighbouring maps differ in
+/// a single variable.
+///
+/// # Panics
+///
+/// If `vars` does not have 2 to 6 variables.
+pub fn karnaugh(
+    expr: &Expr,
+    dont_care: Option<&Expr>,
+    vars: &[String],
+    groups: &[Implicant],
+) -> Result<String, EvalError> {
+    assert!(
+        (2..=6).contains(&vars.len()),
+        "Karnaugh maps need 2 to 6 variables"
+    );
+    let values = make_table(vars)
+        .iter()
+        .map(|row| match dont_care {
+            Some(dc) if eval(dc, row)? => Ok('X'),
+            _ => Ok(if eval(expr, row)? { '1' } else { '0' }),
+        })
+        .collect::<Result<Vec<_>, EvalError>>()?;
+    let cell = |row: usize| {
+        let value = values[row];
+        let marks = (0..groups.len())
+            .filter(|g| groups[*g].covers(row))
+            .map(|g| char::from(b'A' + (g % 26) as u8));
+        std::iter::once(value).chain(marks).collect::<String>()
+    };
+
+    // the variables picking one of the maps, then the rows and the columns of each
+    let maps = vars.len().saturating_sub(4);
+    let rows = (vars.len() - maps) / 2;
+    let cols = vars.len() - maps - rows;
+    let width = (0..values.len())
+        .map(|r| cell(r).len())
+        .chain([cols])
+        .max()
+        .unwrap();
+
+    let corner = format!(
+        "{}\\{}",
+        vars[maps..maps + rows].concat(),
+        vars[maps + rows..].concat()
+    );
+    let render = |map: usize| {
+        let mut lines = vec![];
+        let header = gray(cols)
+            .into_iter()
+            .map(|c| format!("{:width$}", bits(c, cols)))
+            .collect::<Vec<_>>();
+        lines.push(format!("{corner} | {} |", header.join(" | ")));
+        lines.push(
+            lines[0]
+                .chars()
+                .map(|c| if c == '|' { '+' } else { '-' })
+                .collect(),
+        );
+        for r in gray(rows) {
+            let cells = gray(cols)
+                .into_iter()
+                .map(|c| format!("{:width$}", cell(map << (rows + cols) | r << cols | c)))
+                .collect::<Vec<_>>();
+            lines.push(format!(
+                "{:>w$} | {} |",
+                bits(r, rows),
+                cells.join(" | "),
+                w = corner.chars().count()
+            ));
+        }
+        lines
+    };
+    if maps == 0 {
+        return Ok(render(0).join("\n"));
+    }
+
+    // with 6 variables the first picks the row of maps and the second the column
+    let (map_rows, map_cols) = if maps == 1 { (1, 2) } else { (2, 2) };
+    let mut out = vec![];
+    for mr in 0..map_rows {
+        let rendered = (0..map_cols)
+            .map(|mc| mr * map_cols + mc)
+            .map(|map| {
+                let label = (0..maps)
+                    .map(|i| format!("{}={}", vars[i], map >> (maps - 1 - i) & 1))
+                    .collect::<Vec<_>>();
+                let mut lines = vec![label.join(" ")];
+                lines.extend(render(map));
+                lines
+            })
+            .collect::<Vec<_>>();
+        let w = rendered[0].iter().map(|l| l.chars().count()).max().unwrap();
+        for i in 0..rendered[0].len() {
+            let line = rendered
+                .iter()
+                .map(|lines| format!("{:w$}", lines[i]))
+                .collect::<Vec<_>>();
+            out.push(line.join("   ").trim_end().to_string());
+        }
+        out.push(String::new());
+    }
+    out.pop();
+    Ok(out.join("\n"))
+}
+
+/// Renders the Karnaugh map of `expr` over `vars` with the terms of one of its minimal
+/// forms marked, followed by a line naming each one
+///
+/// # Panics
+///
+/// If `vars` does not have 2 to 6 variables.
+pub fn kmap(
+    expr: &Expr,
+    dont_care: Option<&Expr>,
+    vars: &[String],
+    groups: Groups,
+) -> Result<String, EvalError> {
+    let min = match groups {
+        Groups::Plain => return karnaugh(expr, dont_care, vars, &[]),
+        Groups::Products => minimize(expr, dont_care, vars)?,
+        Groups::Sums => minimize_pos(expr, dont_care, vars)?,
+    };
+    let cover = min.cover.iter().map(|p| min.primes[*p]).collect::<Vec<_>>();
+    let mut out = karnaugh(expr, dont_care, vars, &cover)?;
+    for (i, p) in min.cover.iter().enumerate() {
+        out += &format!("\n{}: {}", char::from(b'A' + (i % 26) as u8), min.term(*p));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_expr;
+
+    fn map(f: &str, groups: Groups) -> String {
+        let expr = parse_expr(f).unwrap();
+        let mut vars = crate::get_vars(&expr).into_iter().collect::<Vec<_>>();
+        vars.sort();
+        kmap(&expr, None, &vars, groups).unwrap()
+    }
+
+    #[test]
+    fn layout() {
+        assert_eq!(
+            map("a ^ b", Groups::Plain),
+            "\
+a\\b | 0 | 1 |
+----+---+---+
+  0 | 0 | 1 |
+  1 | 1 | 0 |"
+        );
+        // the columns go 00 01 11 10, so that neighbours differ in one variable
+        assert_eq!(
+            map("a & b | c", Groups::Plain),
+            "\
+a\\bc | 00 | 01 | 11 | 10 |
+-----+----+----+----+----+
+   0 | 0  | 1  | 1  | 0  |
+   1 | 0  | 1  | 1  | 1  |"
+        );
+        assert_eq!(
+            map("!b & !d | a & b & c", Groups::Plain),
+            "\
+ab\\cd | 00 | 01 | 11 | 10 |
+------+----+----+----+----+
+   00 | 1  | 0  | 0  | 1  |
+   01 | 0  | 0  | 0  | 0  |
+   11 | 0  | 0  | 1  | 1  |
+   10 | 1  | 0  | 0  | 1  |"
+        );
+    }
+
+    #[test]
+    fn groups_wrap_around() {
+        // `!b & !d` is the four corners
+        assert_eq!(
+            map("!b & !d | a & b & c", Groups::Products),
+            "\
+ab\\cd | 00 | 01 | 11 | 10 |
+------+----+----+----+----+
+   00 | 1A | 0  | 0  | 1A |
+   01 | 0  | 0  | 0  | 0  |
+   11 | 0  | 0  | 1B | 1B |
+   10 | 1A | 0  | 0  | 1A |
+A: !b & !d
+B: a & b & c"
+        );
+    }
+
+    #[test]
+    fn sums_cover_the_zeros() {
+        assert_eq!(
+            map("!b & !d | a & b & c", Groups::Sums),
+            "\
+ab\\cd | 00  | 01  | 11  | 10  |
+------+-----+-----+-----+-----+
+   00 | 1   | 0A  | 0A  | 1   |
+   01 | 0BC | 0BC | 0B  | 0B  |
+   11 | 0C  | 0C  | 1   | 1   |
+   10 | 1   | 0A  | 0A  | 1   |
+A: b | !d
+B: a | !b
+C: !b | c"
+        );
+    }
+}
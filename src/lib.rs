@@ -0,0 +1,499 @@
+//! Parsing and evaluation of propositional logic formulas.
+//!
+//! A program is a list of definitions like `f = a & b => c;`, optionally with don't-care
+//! sets like `dc f = !a;`, which can be read with [`parse_program`]. Definitions can use
+//! each other by name, in any order, as long as none of them ends up using itself. They
+//! are compiled once each by [`Program::compile`], [`Program::constrain`], [`classify_in`]
+//! and [`find_difference_in`], where [`Program::expand`] copies them for every use.
+//! Connectives can be declared like `maj(x, y, z) = x & y | y & z;` and called like
+//! `maj(a, b, !c)` after that, see [`Macro`]. Single expressions are read with
+//! [`parse_expr`], evaluated with [`eval`], and a whole program can be tabulated with
+//! [`truth_table`].
+//!
+//! [`classify`] tells tautologies, contradictions and contingent formulas apart and
+//! [`find_difference`] checks whether two formulas are equivalent. Both compile formulas
+//! into a [`Bdd`], which gives canonical forms that can be compared and have their models
+//! counted without a table, and use the SAT solver in [`sat`] for formulas with too many
+//! variables to tabulate.
+//! [`count_models`] counts models exactly and [`probability`] gives the chance of a formula
+//! being true when its variables are random. [`models`] lists the satisfying assignments one
+//! at a time.
+//!
+//! [`nnf`], [`cnf`] and [`dnf`] rewrite a formula into normal forms, [`canonical_cnf`] and
+//! [`canonical_dnf`] build the full maxterm and minterm forms from its truth table. An [`Expr`]
+//! displays in the same syntax it is parsed from.
+//!
+//! [`minimize`] finds a minimal sum of products and [`minimize_pos`] a minimal product of
+//! sums, which [`kmap`] can show on a Karnaugh map.
+//!
+//! Problems are exchanged with other SAT tools in DIMACS CNF with [`read_dimacs`] and
+//! [`write_dimacs`]. Syntax trees and decision diagrams can be drawn with Graphviz using
+//! [`expr_dot`] and [`bdd_dot`].
+//!
+//! Operators from the tightest: `!`, then `&` and `nand`, `^` (also written `xor` or `!=`),
+//! `|` and `nor`, `=>`, `<=` and `<=>` (also written `xnor`); `=>` is right-associative,
+//! the rest are left-associative. Formulas pasted from elsewhere can also use `¬ ∧ ∨ → ↔ ⊕ ⊤ ⊥`
+//! (and `⇒ ⟹ ⇔ ⟺`), `~ && ||`, the words `not and or implies iff`, and `0` and `1`
+//! for the constants.
+//!
+//! Names start with a letter or `_` and go on with letters, digits and `_`, like `x1` or
+//! `req_valid`, and can end with indices like `a[3]` or `m[0][2]`. The constants and the
+//! operator words are reserved and cannot be used as names, even indexed like `true[1]`.
+
+mod analysis;
+mod bdd;
+mod bigint;
+mod count;
+mod dimacs;
+mod dot;
+mod error;
+mod eval;
+mod kmap;
+mod minimize;
+mod normal;
+mod parser;
+pub mod sat;
+#[cfg(test)]
+mod testing;
+mod tseitin;
+
+pub use analysis::{
+    classify, classify_in, find_difference, find_difference_in, Analysis, Classification,
+};
+pub use bdd::{Bdd, NodeId};
+pub use bigint::BigUint;
+pub use count::{count_cnf, count_models, probability};
+pub use dimacs::{read_dimacs, write_dimacs};
+pub use dot::{bdd_dot, expr_dot};
+pub use error::EvalError;
+pub use eval::{
+    eval, get_vars, make_table, order_vars, truth_table, Assignment, TruthTable, VarOrder,
+    TABLE_LIMIT,
+};
+pub use kmap::{karnaugh, kmap, Groups};
+pub use minimize::{minimize, minimize_pos, Implicant, Minimization};
+pub use normal::{
+    canonical_cnf, canonical_dnf, cnf, cnf_clauses, dnf, dnf_terms, eliminate, nnf, Literal,
+};
+pub use parser::{parse_expr, parse_program};
+pub use sat::{falsify, models, satisfy, Models};
+pub use tseitin::{plaisted_greenbaum, tseitin, Encoding};
+
+use std::collections::HashMap;
+use std::fmt;
+
+use eval::get_vars_;
+
+/// A propositional formula
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    True,
+    False,
+    /// A variable
+    Term(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Imply(Box<Expr>, Box<Expr>),
+    Equiv(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+    Nand(Box<Expr>, Box<Expr>),
+    Nor(Box<Expr>, Box<Expr>),
+    /// `l <= r`, true unless `r` is true and `l` false
+    Converse(Box<Expr>, Box<Expr>),
+    /// A call of a connective declared by the user, with an argument for each of
+    /// its parameters
+    Apply(Box<Macro>, Vec<Expr>),
+}
+
+/// A connective declared by the user like `maj(x, y, z) = x & y | y & z | x & z;`,
+/// its body only uses its parameters
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Macro {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Expr,
+}
+
+impl Macro {
+    /// The body with each parameter replaced by the corresponding argument
+    pub fn apply(&self, args: &[Expr]) -> Expr {
+        let values = self.params.iter().zip(args).collect::<HashMap<_, _>>();
+        substitute(&self.body, &values)
+    }
+}
+
+fn substitute(expr: &Expr, values: &HashMap<&String, &Expr>) -> Expr {
+    let sub = |e: &Expr| Box::new(substitute(e, values));
+    match expr {
+        Expr::Term(t) => values.get(t).map_or_else(|| expr.clone(), |e| (*e).clone()),
+        Expr::True | Expr::False => expr.clone(),
+        Expr::Not(e) => Expr::Not(sub(e)),
+        Expr::And(l, r) => Expr::And(sub(l), sub(r)),
+        Expr::Or(l, r) => Expr::Or(sub(l), sub(r)),
+        Expr::Imply(l, r) => Expr::Imply(sub(l), sub(r)),
+        Expr::Equiv(l, r) => Expr::Equiv(sub(l), sub(r)),
+        Expr::Xor(l, r) => Expr::Xor(sub(l), sub(r)),
+        Expr::Nand(l, r) => Expr::Nand(sub(l), sub(r)),
+        Expr::Nor(l, r) => Expr::Nor(sub(l), sub(r)),
+        Expr::Converse(l, r) => Expr::Converse(sub(l), sub(r)),
+        Expr::Apply(m, args) => Expr::Apply(
+            m.clone(),
+            args.iter().map(|a| substitute(a, values)).collect(),
+        ),
+    }
+}
+
+/// Named formulas, as read by [`parse_program`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    pub definitions: Vec<(String, Expr)>,
+    /// The don't-care sets given with `dc f = ...;`, the value of `f` does not matter
+    /// where its don't-care set is true
+    pub dont_cares: HashMap<String, Expr>,
+    /// The connectives declared with parameters, their calls in the definitions are
+    /// [`Expr::Apply`] nodes
+    pub macros: Vec<Macro>,
+}
+
+impl Program {
+    pub fn definition(&self, name: &str) -> Option<&Expr> {
+        self.definitions
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, e)| e)
+    }
+
+    pub fn dont_care(&self, name: &str) -> Option<&Expr> {
+        self.dont_cares.get(name)
+    }
+
+    /// The indices of the definitions used by `expr`, in order of appearance
+    fn uses(&self, expr: &Expr) -> Vec<usize> {
+        let mut uses = vec![];
+        for v in get_vars_(expr) {
+            if let Some(i) = self.definitions.iter().position(|(n, _)| *n == v) {
+                if !uses.contains(&i) {
+                    uses.push(i);
+                }
+            }
+        }
+        uses
+    }
+
+    /// Whether some definition uses the definition `name`
+    pub fn is_used(&self, name: &str) -> bool {
+        self.definitions
+            .iter()
+            .any(|(_, e)| get_vars_(e).iter().any(|v| v == name))
+    }
+
+    /// Adds the definition `i` to `order` after the definitions it uses, depth-first with
+    /// `path` holding the definitions being visited
+    fn visit(
+        &self,
+        i: usize,
+        path: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> Result<(), EvalError> {
+        if order.contains(&i) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&j| j == i) {
+            let cycle = path[start..]
+                .iter()
+                .chain([&i])
+                .map(|&j| self.definitions[j].0.clone())
+                .collect();
+            return Err(EvalError::RecursiveDefinition(cycle));
+        }
+        path.push(i);
+        for j in self.uses(&self.definitions[i].1) {
+            self.visit(j, path, order)?;
+        }
+        path.pop();
+        order.push(i);
+        Ok(())
+    }
+
+    /// The indices of the definitions, each one after the definitions it uses
+    ///
+    /// Fails if a definition uses itself, directly or through others.
+    pub fn dependency_order(&self) -> Result<Vec<usize>, EvalError> {
+        let mut order = vec![];
+        for i in 0..self.definitions.len() {
+            self.visit(i, &mut vec![], &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// The indices of the definitions used by `expr`, directly or through others, each
+    /// one after the definitions it uses
+    pub fn dependencies(&self, expr: &Expr) -> Result<Vec<usize>, EvalError> {
+        let mut order = vec![];
+        for i in self.uses(expr) {
+            self.visit(i, &mut vec![], &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// The variables `expr` depends on, through the definitions it uses too, in the given
+    /// order and leaving out the names of definitions
+    pub fn inputs(&self, expr: &Expr, order: &VarOrder) -> Result<Vec<String>, EvalError> {
+        let exprs = std::iter::once(expr)
+            .chain(
+                self.dependencies(expr)?
+                    .into_iter()
+                    .map(|i| &self.definitions[i].1),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut vars = order_vars(&exprs, order);
+        vars.retain(|v| self.definition(v).is_none());
+        Ok(vars)
+    }
+
+    /// Builds the diagram of `expr` in `bdd`, compiling each definition it uses once
+    /// instead of once for every use as with [`Program::expand`]
+    pub fn compile(&self, expr: &Expr, bdd: &mut Bdd) -> Result<NodeId, EvalError> {
+        let mut nodes = HashMap::new();
+        for i in self.dependencies(expr)? {
+            let (name, e) = &self.definitions[i];
+            let f = bdd.compile_with(e, &nodes);
+            nodes.insert(name.clone(), f);
+        }
+        Ok(bdd.compile_with(expr, &nodes))
+    }
+
+    /// The value of `expr` under `values`, evaluating each definition it uses once
+    pub fn eval(&self, expr: &Expr, values: &Assignment) -> Result<bool, EvalError> {
+        let mut values = values.clone();
+        for i in self.dependencies(expr)? {
+            let (name, e) = &self.definitions[i];
+            let value = eval(e, &values)?;
+            values.insert(name.clone(), value);
+        }
+        eval(expr, &values)
+    }
+
+    /// `expr` and `name <=> e` for every definition `name = e;` it uses, directly or not
+    ///
+    /// Its models are the ones of the expanded `expr` with the values of the definitions
+    /// added, but it has a single copy of each definition, which suits the SAT solver.
+    pub fn constrain(&self, expr: &Expr) -> Result<Expr, EvalError> {
+        let mut constrained = expr.clone();
+        for i in self.dependencies(expr)? {
+            let (name, e) = &self.definitions[i];
+            let equiv = Expr::Equiv(Expr::Term(name.clone()).into(), e.clone().into());
+            constrained = Expr::And(constrained.into(), equiv.into());
+        }
+        Ok(constrained)
+    }
+
+    /// `expr` with the definitions it uses replaced by their expressions, recursively
+    ///
+    /// The definitions must not be recursive, which [`parse_program`] makes sure of. A
+    /// definition is copied for every use, so definitions using others several times
+    /// can blow up, [`Program::compile`] and [`Program::constrain`] do not.
+    pub fn expand(&self, expr: &Expr) -> Expr {
+        let expand = |e: &Expr| Box::new(self.expand(e));
+        match expr {
+            Expr::Term(t) => match self.definition(t) {
+                Some(e) => self.expand(e),
+                None => expr.clone(),
+            },
+            Expr::True | Expr::False => expr.clone(),
+            Expr::Not(e) => Expr::Not(expand(e)),
+            Expr::And(l, r) => Expr::And(expand(l), expand(r)),
+            Expr::Or(l, r) => Expr::Or(expand(l), expand(r)),
+            Expr::Imply(l, r) => Expr::Imply(expand(l), expand(r)),
+            Expr::Equiv(l, r) => Expr::Equiv(expand(l), expand(r)),
+            Expr::Xor(l, r) => Expr::Xor(expand(l), expand(r)),
+            Expr::Nand(l, r) => Expr::Nand(expand(l), expand(r)),
+            Expr::Nor(l, r) => Expr::Nor(expand(l), expand(r)),
+            Expr::Converse(l, r) => Expr::Converse(expand(l), expand(r)),
+            Expr::Apply(m, args) => {
+                Expr::Apply(m.clone(), args.iter().map(|a| self.expand(a)).collect())
+            }
+        }
+    }
+
+    /// The variables of the definitions and of their don't-care sets in the given order,
+    /// leaving out the names of definitions
+    pub fn vars(&self, order: &VarOrder) -> Vec<String> {
+        let exprs = self
+            .definitions
+            .iter()
+            .map(|(_, e)| e)
+            // in the same order as the columns of the table
+            .chain(
+                self.definitions
+                    .iter()
+                    .filter_map(|(n, _)| self.dont_care(n)),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut vars = order_vars(&exprs, order);
+        vars.retain(|v| self.definition(v).is_none());
+        vars
+    }
+}
+
+impl Expr {
+    /// How tightly the expression binds, higher is tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Equiv(..) => 1,
+            Expr::Converse(..) => 2,
+            Expr::Imply(..) => 3,
+            Expr::Or(..) | Expr::Nor(..) => 4,
+            Expr::Xor(..) => 5,
+            Expr::And(..) | Expr::Nand(..) => 6,
+            Expr::True | Expr::False | Expr::Term(_) | Expr::Not(_) | Expr::Apply(..) => 7,
+        }
+    }
+
+    /// Writes `self`, in parentheses if it binds looser than `min`
+    fn fmt_prec(&self, f: &mut fmt::Formatter<'_>, min: u8) -> fmt::Result {
+        if self.precedence() < min {
+            write!(f, "(")?;
+            self.fmt_prec(f, 0)?;
+            return write!(f, ")");
+        }
+        let (op, l, r, left, right) = match self {
+            Expr::True => return write!(f, "true"),
+            Expr::False => return write!(f, "false"),
+            Expr::Term(v) => return write!(f, "{v}"),
+            Expr::Not(e) => {
+                write!(f, "!")?;
+                return e.fmt_prec(f, 7);
+            }
+            Expr::Apply(m, args) => {
+                write!(f, "{}(", m.name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    arg.fmt_prec(f, 0)?;
+                }
+                return write!(f, ")");
+            }
+            Expr::And(l, r) => ("&", l, r, 6, 7),
+            Expr::Nand(l, r) => ("nand", l, r, 6, 7),
+            Expr::Xor(l, r) => ("^", l, r, 5, 6),
+            Expr::Or(l, r) => ("|", l, r, 4, 5),
+            Expr::Nor(l, r) => ("nor", l, r, 4, 5),
+            Expr::Imply(l, r) => ("=>", l, r, 4, 3),
+            Expr::Converse(l, r) => ("<=", l, r, 2, 3),
+            Expr::Equiv(l, r) => ("<=>", l, r, 1, 2),
+        };
+        l.fmt_prec(f, left)?;
+        write!(f, " {op} ")?;
+        r.fmt_prec(f, right)
+    }
+}
+
+/// Writes the formula in the syntax accepted by [`parse_expr`],
+/// with only the parentheses needed to keep its structure
+///
+/// The exception is calls of connectives like `maj(a, b, c)`, which [`parse_expr`] rejects
+/// as it knows no connectives. They only parse back in a program declaring them first.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Definitions each using the one before it three times, which expanded would be
+    /// billions of nodes, over three variables or over a new one at every level
+    fn chain(levels: usize, new_vars: bool) -> Program {
+        let mut source = "d0 = a & b | c;".to_string();
+        for i in 0..levels {
+            let x = if new_vars {
+                format!("x{i}")
+            } else {
+                "c".to_string()
+            };
+            source += &format!(" d{} = d{i} ^ (d{i} & {x}) | !d{i} & a;", i + 1);
+        }
+        parse_program(&source).unwrap()
+    }
+
+    #[test]
+    fn chains_are_not_expanded() {
+        let start = std::time::Instant::now();
+        for new_vars in [false, true] {
+            let program = chain(40, new_vars);
+            let (last, before) = (Expr::Term("d40".into()), Expr::Term("d39".into()));
+            let analysis = classify_in(&program, &last).unwrap();
+            assert_eq!(analysis.class, Classification::Contingent);
+            for w in [analysis.satisfying.unwrap(), analysis.falsifying.unwrap()] {
+                assert!(w.keys().all(|v| program.definition(v).is_none()));
+            }
+            let w = find_difference_in(&program, &last, &before)
+                .unwrap()
+                .unwrap();
+            assert_ne!(program.eval(&last, &w), program.eval(&before, &w));
+            let vars = program.inputs(&last, &VarOrder::Sorted).unwrap();
+            assert_eq!(vars.len(), if new_vars { 43 } else { 3 });
+        }
+        let mut bdd = Bdd::new();
+        let f = chain(40, false).compile(&Expr::Term("d40".into()), &mut bdd);
+        assert_eq!(bdd.count(f.unwrap()).to_string(), "4");
+        assert!(start.elapsed().as_secs() < 5, "took {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn functions_are_their_bodies() {
+        let program = parse_program(
+            "maj(x, y, z) = x & y | y & z | x & z; swap(x, y) = x & !y;
+             f = maj(a, !b, c ^ d) | swap(d, maj(c, c, a)); g = swap(f, a);",
+        )
+        .unwrap();
+        let by_hand =
+            parse_expr("(a & !b | !b & (c ^ d) | a & (c ^ d)) | d & !(c & c | c & a | c & a)")
+                .unwrap();
+        let f = program.definition("f").unwrap();
+        assert_eq!(f.to_string(), "maj(a, !b, c ^ d) | swap(d, maj(c, c, a))");
+        let expanded = program.expand(&Expr::Term("g".into()));
+        assert_eq!(
+            expanded,
+            Expr::Apply(
+                program.macros[1].clone().into(),
+                vec![f.clone(), Expr::Term("a".into())]
+            )
+        );
+        let g = Expr::And(
+            by_hand.clone().into(),
+            Expr::Not(Expr::Term("a".into()).into()).into(),
+        );
+
+        let vars = ["a", "b", "c", "d"].map(String::from);
+        for row in make_table(&vars) {
+            assert_eq!(eval(f, &row), eval(&by_hand, &row), "{row:?}");
+            let g_value = program.eval(&Expr::Term("g".into()), &row);
+            assert_eq!(g_value, eval(&g, &row), "{row:?}");
+            assert_eq!(eval(&expanded, &row), eval(&g, &row), "{row:?}");
+        }
+        assert_eq!(count_models(f), count_models(&by_hand));
+        assert_eq!(count_models(&expanded), count_models(&g));
+        let mut bdd = Bdd::new();
+        let compiled = program.compile(&Expr::Term("g".into()), &mut bdd).unwrap();
+        assert_eq!(compiled, bdd.compile(&g));
+        assert_eq!(bdd.compile(f), bdd.compile(&by_hand));
+    }
+
+    #[test]
+    fn parameters_are_not_captured() {
+        let program = parse_program("swap(x, y) = x & !y; f = swap(y, x);").unwrap();
+        let Some(Expr::Apply(swap, args)) = program.definition("f") else {
+            panic!()
+        };
+        // the parameters are replaced at once, so `x` does not become `y` and back
+        assert_eq!(swap.apply(args), parse_expr("y & !x").unwrap());
+    }
+}
@@ -1,210 +1,518 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::env;
+use std::fs;
 use std::io::{self, BufRead};
 
-use untwine::{parse, parser};
-
-#[derive(Debug, Clone)]
-enum Expr {
-    True,
-    False,
-    Term(String),
-    Not(Box<Expr>),
-    And(Box<Expr>, Box<Expr>),
-    Or(Box<Expr>, Box<Expr>),
-    Imply(Box<Expr>, Box<Expr>),
-    Equiv(Box<Expr>, Box<Expr>),
+use logic_parser::{
+    bdd_dot, canonical_cnf, canonical_dnf, classify_in, cnf, count_models, dnf, expr_dot,
+    find_difference_in, get_vars, kmap, minimize, minimize_pos, models, nnf, parse_program,
+    read_dimacs, truth_table, write_dimacs, Bdd, EvalError, Expr, Groups, Program, TruthTable,
+    VarOrder, TABLE_LIMIT,
+};
+
+/// Joins the cells of one line of the truth table, variables on the left
+fn table_line(vars: &[String], defs: &[String]) -> String {
+    if vars.is_empty() {
+        return format!("| {} |", defs.join(" | "));
+    }
+    format!("| {} ||| {} |", vars.join(" | "), defs.join(" | "))
+}
+
+/// Formats an assignment like `a=1 b=0`, in the order of `vars`
+fn assignment(vars: &[String], values: &HashMap<String, bool>) -> String {
+    let cells = vars
+        .iter()
+        .filter_map(|v| values.get(v).map(|b| format!("{v}={}", *b as i32)))
+        .collect::<Vec<_>>();
+    if cells.is_empty() {
+        return "any assignment".to_string();
+    }
+    cells.join(" ")
 }
 
-parser! {
-    w = #["\n\r\t "]*;
-    ident = <('a'-'z' | 'A'-'Z')+> -> &str;
-    operator: val=<("&" | "|" | "=>" | "<=>")> -> String { val.to_string() }
+/// What the REPL remembers between lines
+#[derive(Default)]
+struct Repl {
+    order: VarOrder,
+    /// The definitions from the last program
+    program: Program,
+    /// Whether the definitions used by others are left out of the table, instead of
+    /// being shown as intermediate columns
+    inline: bool,
+}
 
+impl Repl {
+    fn handle(&mut self, line: &str) -> Result<(), EvalError> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        // `order = ...;` and the like are still definitions
+        if words.iter().take(2).any(|w| w.contains('=')) {
+            return self.run(line);
+        }
+        match words.as_slice() {
+            ["order", rest @ ..] => {
+                self.order = match rest {
+                    [] => self.order.clone(),
+                    ["sorted"] => VarOrder::Sorted,
+                    ["appearance"] => VarOrder::FirstAppearance,
+                    vars => VarOrder::Custom(vars.iter().map(|v| v.to_string()).collect()),
+                };
+                println!("variable order: {}", self.order);
+            }
+            ["defs", rest @ ..] => self.defs(rest),
+            ["equiv", f, g] => self.equiv(f, g)?,
+            ["equiv", ..] => eprintln!("usage: equiv <definition> <definition>"),
+            [form @ ("nnf" | "cnf" | "dnf"), f] => self.normal_form(form, f, false)?,
+            [form @ ("cnf" | "dnf"), f, "canonical"] => self.normal_form(form, f, true)?,
+            ["nnf", ..] => eprintln!("usage: nnf <definition>"),
+            [form @ ("cnf" | "dnf"), ..] => eprintln!("usage: {form} <definition> [canonical]"),
+            ["dimacs", f] => print!("{}", write_dimacs(&self.definition(f)?)),
+            ["dimacs", ..] => eprintln!("usage: dimacs <definition>"),
+            ["load", name, path] => self.load(name, path),
+            ["load", ..] => eprintln!("usage: load <definition> <file>"),
+            ["minimize", f] => self.minimize(f, false)?,
+            ["minimize", f, "pos"] => self.minimize(f, true)?,
+            ["minimize", ..] => eprintln!("usage: minimize <definition> [pos]"),
+            ["kmap", f] => self.kmap(f, Groups::Plain)?,
+            ["kmap", f, "groups"] => self.kmap(f, Groups::Products)?,
+            ["kmap", f, "pos"] => self.kmap(f, Groups::Sums)?,
+            ["kmap", ..] => eprintln!("usage: kmap <definition> [groups|pos]"),
+            ["bdd", f] => self.bdd(f, false)?,
+            ["bdd", f, "sift"] => self.bdd(f, true)?,
+            ["bdd", ..] => eprintln!("usage: bdd <definition> [sift]"),
+            ["dot", f] => println!("{}", expr_dot(f, &self.definition(f)?)),
+            ["dot", f, "bdd"] => println!("{}", self.bdd_dot(f)?),
+            ["dot", ..] => eprintln!("usage: dot <definition> [bdd]"),
+            ["count", f, weights @ ..] => self.count(f, weights)?,
+            ["count", ..] => eprintln!("usage: count <definition> [variable=probability...]"),
+            ["models", f, rest @ ..] => self.models(f, rest)?,
+            ["models", ..] => eprintln!("usage: models <definition> [variable...] [limit <n>]"),
+            _ => self.run(line)?,
+        }
+        Ok(())
+    }
 
-    term: term=ident -> Expr {
-        match term {
-            "true" => Expr::True,
-            "false" => Expr::False,
-            t => Expr::Term(t.to_string())
+    /// Sets whether the definitions used by others are inlined or shown as columns
+    fn defs(&mut self, args: &[&str]) {
+        match args {
+            [] => {}
+            ["inline"] => self.inline = true,
+            ["columns"] => self.inline = false,
+            _ => return eprintln!("usage: defs [inline|columns]"),
         }
+        let shown = if self.inline { "inlined" } else { "columns" };
+        println!("definitions used by others: {shown}");
     }
 
-    not: "!" w e=expr -> Expr { Expr::Not(e.into()) }
-    unary = w (term | not | ("(" expr ")")) w -> Expr;
-    // solution from untwine doc examples
-    binary: left=unary w rest=(w operator w binary)* -> Expr {
-        rest.into_iter().fold(left, |l, (op, r)| {
-            let typ = match op.as_str() {
-                "&" => Expr::And,
-                "|" => Expr::Or,
-                "=>" => Expr::Imply,
-                "<=>" => Expr::Equiv,
-                x => unreachable!("found operator {}", x),
-            };
-            typ(l.into(), r.into())
-        })
+    /// A definition as written, using the others by name
+    fn lookup(&self, name: &str) -> Result<&Expr, EvalError> {
+        self.program
+            .definition(name)
+            .ok_or_else(|| EvalError::UnknownDefinition(name.to_string()))
     }
 
-    pub expr = w (binary | unary) w -> Expr;
-    pub start: start=(w ident w "=" w expr ";" w)* -> Vec<(String, Expr)> {
-        start.into_iter().map(|(s, e)| (s.to_string(), e)).collect::<Vec<_>>()
+    /// A definition with the definitions it uses expanded, for the commands printing it
+    fn definition(&self, name: &str) -> Result<Expr, EvalError> {
+        Ok(self.program.expand(self.lookup(name)?))
     }
-}
 
-fn interpret(expr: Expr, vars: HashMap<String, bool>) -> bool {
-    let symbols = get_vars(expr.clone());
-    for s in symbols {
-        if !vars.contains_key(&s) {
-            panic!("symbol \"{s}\" not found in vars!");
+    /// The variables of a definition and of its don't-care set, through the definitions
+    /// they use
+    fn table_vars(&self, name: &str) -> Result<Vec<String>, EvalError> {
+        let expr = self.lookup(name)?.clone();
+        let both = match self.program.dont_care(name) {
+            Some(dc) => Expr::And(expr.into(), dc.clone().into()),
+            None => expr,
+        };
+        self.program.inputs(&both, &self.order)
+    }
+
+    /// A definition and its don't-care set as formulas over `vars` only, read off their
+    /// decision diagrams so that the definitions they use are not copied for every use
+    fn tabulated(&self, name: &str, vars: &[String]) -> Result<(Expr, Option<Expr>), EvalError> {
+        let mut bdd = Bdd::with_order(vars);
+        let f = self.program.compile(self.lookup(name)?, &mut bdd)?;
+        let dc = match self.program.dont_care(name) {
+            Some(e) => Some(self.program.compile(e, &mut bdd)?),
+            None => None,
+        };
+        Ok((bdd.to_expr(f), dc.map(|dc| bdd.to_expr(dc))))
+    }
+
+    fn equiv(&self, f: &str, g: &str) -> Result<(), EvalError> {
+        let (e1, e2) = (self.lookup(f)?, self.lookup(g)?);
+        match find_difference_in(&self.program, e1, e2)? {
+            None => println!("{f} ≡ {g}"),
+            Some(w) => {
+                let both = Expr::And(e1.clone().into(), e2.clone().into());
+                let vars = self.program.inputs(&both, &self.order)?;
+                println!(
+                    "{f} ≢ {g}: {f}={}, {g}={} for {}",
+                    self.program.eval(e1, &w)? as i32,
+                    self.program.eval(e2, &w)? as i32,
+                    assignment(&vars, &w)
+                );
+            }
         }
+        Ok(())
     }
-    interpret_(expr, vars)
-}
-fn interpret_(expr: Expr, vars: HashMap<String, bool>) -> bool {
-    // REMINDER: || and && SHORT-CIRCUIT
-    // which means that if there is an unknown symbol, it gets ignored
-    match expr {
-        Expr::True => true,
-        Expr::False => false,
-        Expr::Term(t) => {
-            let Some(val) = vars.get(&t) else {
-                panic!("Value for \"{t}\" not found!");
-            };
-            *val
-        }
-        Expr::Not(e) => !interpret(*e, vars),
-        Expr::Or(l, r) => interpret(*l, vars.clone()) | interpret(*r, vars),
-        Expr::And(l, r) => interpret(*l, vars.clone()) & interpret(*r, vars),
-        Expr::Imply(l, r) => !interpret(*l, vars.clone()) | interpret(*r, vars),
-        Expr::Equiv(l, r) => !(interpret(*l, vars.clone()) ^ interpret(*r, vars)),
+
+    /// Prints a definition in a normal form, as a definition that can be entered again
+    fn normal_form(&self, form: &str, f: &str, canonical: bool) -> Result<(), EvalError> {
+        let result = if canonical {
+            let vars = self.program.inputs(self.lookup(f)?, &self.order)?;
+            if vars.len() > TABLE_LIMIT {
+                println!("too many variables for a truth table ({})", vars.len());
+                return Ok(());
+            }
+            let (expr, _) = self.tabulated(f, &vars)?;
+            match form {
+                "cnf" => canonical_cnf(&expr, &vars)?,
+                _ => canonical_dnf(&expr, &vars)?,
+            }
+        } else {
+            let expr = &self.definition(f)?;
+            match form {
+                "nnf" => nnf(expr),
+                "cnf" => cnf(expr),
+                _ => dnf(expr),
+            }
+        };
+        println!("{f} = {result};");
+        Ok(())
     }
-}
 
-fn get_vars(expr: Expr) -> HashSet<String> {
-    let mut set = HashSet::new();
-    set.extend(get_vars_(expr));
-    set
-}
-fn get_vars_(expr: Expr) -> Vec<String> {
-    match expr {
-        Expr::True => vec![],
-        Expr::False => vec![],
-        Expr::Term(t) => vec![t],
-        Expr::Not(e) => get_vars_(*e),
-        Expr::Or(l, r) => {
-            let mut l1 = get_vars_(*l);
-            let mut r1 = get_vars_(*r);
-            l1.append(&mut r1);
-            l1
-        }
-        Expr::And(l, r) => {
-            let mut l1 = get_vars_(*l);
-            let mut r1 = get_vars_(*r);
-            l1.append(&mut r1);
-            l1
-        }
-        Expr::Imply(l, r) => {
-            let mut l1 = get_vars_(*l);
-            let mut r1 = get_vars_(*r);
-            l1.append(&mut r1);
-            l1
-        }
-        Expr::Equiv(l, r) => {
-            let mut l1 = get_vars_(*l);
-            let mut r1 = get_vars_(*r);
-            l1.append(&mut r1);
-            l1
+    /// Prints the prime implicants of a definition and its minimal sum of products,
+    /// or product of sums if `pos` is set
+    fn minimize(&self, f: &str, pos: bool) -> Result<(), EvalError> {
+        let vars = self.table_vars(f)?;
+        if vars.len() > TABLE_LIMIT {
+            println!("too many variables for a truth table ({})", vars.len());
+            return Ok(());
         }
+        let (expr, dc) = self.tabulated(f, &vars)?;
+        let min = if pos {
+            minimize_pos(&expr, dc.as_ref(), &vars)?
+        } else {
+            minimize(&expr, dc.as_ref(), &vars)?
+        };
+        let kind = if pos { "implicates" } else { "implicants" };
+        println!("prime {kind} ({}), * essential:", vars.join(" "));
+        for (i, prime) in min.primes.iter().enumerate() {
+            let minterms = min
+                .minterms
+                .iter()
+                .filter(|m| prime.covers(**m))
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>();
+            let mark = if min.essential.contains(&i) { '*' } else { ' ' };
+            println!(
+                "{mark} {} {} covers {}",
+                prime.pattern(vars.len()),
+                min.term(i),
+                minterms.join(", ")
+            );
+        }
+        println!("{f} = {};", min.expr());
+        Ok(())
+    }
+
+    /// Prints the Karnaugh map of a definition, with the terms of one of its minimal
+    /// forms marked if asked
+    fn kmap(&self, f: &str, groups: Groups) -> Result<(), EvalError> {
+        let vars = self.table_vars(f)?;
+        if !(2..=6).contains(&vars.len()) {
+            println!(
+                "Karnaugh maps need 2 to 6 variables, {f} has {}",
+                vars.len()
+            );
+            return Ok(());
+        }
+        let (expr, dc) = self.tabulated(f, &vars)?;
+        println!("{}", kmap(&expr, dc.as_ref(), &vars, groups)?);
+        Ok(())
+    }
+
+    /// Prints the size of the decision diagram of a definition and its number of models,
+    /// after reordering the variables if `sift` is set
+    fn bdd(&self, f: &str, sift: bool) -> Result<(), EvalError> {
+        let expr = self.lookup(f)?;
+        let mut bdd = Bdd::with_order(&self.program.inputs(expr, &self.order)?);
+        let mut roots = [self.program.compile(expr, &mut bdd)?];
+        println!(
+            "{f}: {} nodes, order {}",
+            bdd.size(&roots),
+            bdd.order().join(" ")
+        );
+        if sift {
+            bdd.sift(&mut roots);
+            println!(
+                "sifted: {} nodes, order {}",
+                bdd.size(&roots),
+                bdd.order().join(" ")
+            );
+        }
+        let vars = bdd.order().len();
+        println!("true for {} of 2^{vars} assignments", bdd.count(roots[0]));
+        Ok(())
+    }
+
+    /// The decision diagram of a definition as a Graphviz graph
+    fn bdd_dot(&self, f: &str) -> Result<String, EvalError> {
+        let expr = self.lookup(f)?;
+        let mut bdd = Bdd::with_order(&self.program.inputs(expr, &self.order)?);
+        let root = self.program.compile(expr, &mut bdd)?;
+        Ok(bdd_dot(f, &bdd, &[(f.to_string(), root)]))
+    }
+
+    /// Prints the number of models of a definition and its probability of being true,
+    /// with the variables true with the probabilities given like `a=0.9`, or 1/2
+    fn count(&self, f: &str, weights: &[&str]) -> Result<(), EvalError> {
+        let expr = self.lookup(f)?;
+        let mut probabilities = HashMap::new();
+        for w in weights {
+            match w.split_once('=').map(|(v, p)| (v, p.parse::<f64>())) {
+                Some((v, Ok(p))) if (0.0..=1.0).contains(&p) => {
+                    probabilities.insert(v.to_string(), p);
+                }
+                _ => {
+                    eprintln!("expected a probability like `a=0.5`, got `{w}`");
+                    return Ok(());
+                }
+            }
+        }
+        // the definitions used are fixed by the variables, so they do not change the count
+        let mut bdd = Bdd::new();
+        let root = self.program.compile(expr, &mut bdd)?;
+        println!(
+            "{f}: true for {} of 2^{} assignments, probability {}",
+            count_models(&self.program.constrain(expr)?),
+            self.program.inputs(expr, &self.order)?.len(),
+            bdd.probability(root, &probabilities)
+        );
+        Ok(())
+    }
+
+    /// Prints the models of a definition, only the values of the variables in `args` if
+    /// there are any, stopping after `limit <n>` of them if that comes last
+    fn models(&self, f: &str, args: &[&str]) -> Result<(), EvalError> {
+        let expr = self.lookup(f)?;
+        let (projection, limit) = match args {
+            [vars @ .., "limit", n] => match n.parse::<usize>() {
+                Ok(n) if n > 0 => (vars, Some(n)),
+                _ => {
+                    eprintln!("expected a positive number of models, got `{n}`");
+                    return Ok(());
+                }
+            },
+            vars => (vars, None),
+        };
+        let mut vars = vec![];
+        for v in projection {
+            // a variable given twice is shown once
+            if !vars.iter().any(|w| w == v) {
+                vars.push(v.to_string());
+            }
+        }
+        if vars.is_empty() {
+            vars = self.program.inputs(expr, &self.order)?;
+        }
+        // projected on the variables, the definitions used are fixed by them
+        let mut found = models(&self.program.constrain(expr)?, Some(&vars)).peekable();
+        let mut shown = 0;
+        for model in found.by_ref().take(limit.unwrap_or(usize::MAX)) {
+            println!("{}", assignment(&vars, &model));
+            shown += 1;
+        }
+        // only stopped early if there is one more
+        if found.peek().is_some() {
+            println!("{f}: stopped after the first {shown} models");
+        } else {
+            println!("{f}: {shown} found");
+        }
+        Ok(())
     }
-}
 
-fn make_table(vars: HashSet<String>) -> Vec<HashMap<String, bool>> {
-    // assumes there is at least one var
-    // which is reasonable I would say
-    let mut it = vars.iter();
+    /// Reads a DIMACS file into the definition `name`, replacing it if it exists
+    fn load(&mut self, name: &str, path: &str) {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => return eprintln!("error: {path}: {e}"),
+        };
+        let expr = match read_dimacs(&text) {
+            Ok(e) => e,
+            // the error points into the file, not the command
+            Err(e) => return eprintln!("{}", e.report(&text)),
+        };
+        let vars = get_vars(&expr);
+        // they would silently stand for the definitions, or make `name` recursive
+        let mut taken = vars
+            .iter()
+            .filter(|v| *v == name || self.program.definition(v).is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+        if !taken.is_empty() {
+            taken.sort();
+            return eprintln!(
+                "error: {path}: variables named like definitions: {}",
+                taken.join(", ")
+            );
+        }
+        self.program.dont_cares.remove(name);
+        match self.program.definitions.iter_mut().find(|(n, _)| n == name) {
+            Some((_, e)) => *e = expr,
+            None => self.program.definitions.push((name.to_string(), expr)),
+        }
+        println!("{name}: {} variables", vars.len());
+    }
 
-    let first = it.next().unwrap().clone();
-    let mut tables: Vec<HashMap<String, bool>> = vec![
-        HashMap::from([(first.clone(), false)]),
-        HashMap::from([(first, true)]),
-    ];
+    fn run(&mut self, line: &str) -> Result<(), EvalError> {
+        let ast = parse_program(line)?;
+        let order = &self.order;
+        //println!("{:?}", ast.clone());
 
-    for v in it {
-        let mut ls2 = vec![];
-        for table in tables {
-            let mut new_table = table.clone();
-            new_table.insert(v.clone(), false);
-            ls2.push(new_table);
+        // the definitions shown, with the ones they use inlined if they are not shown
+        let shown = ast
+            .definitions
+            .iter()
+            .filter(|(n, _)| !self.inline || !ast.is_used(n))
+            .collect::<Vec<_>>();
+        if self.inline {
+            for (name, expr) in &shown {
+                let expanded = ast.expand(expr);
+                if expanded != *expr {
+                    println!("{name} = {expanded};");
+                }
+            }
+        }
 
-            let mut new_table2 = table.clone();
-            new_table2.insert(v.clone(), true);
+        let vars = ast.vars(order);
+        if ast.definitions.is_empty() {
+            // only connectives were declared, there is nothing to tabulate
+            let macros = ast
+                .macros
+                .iter()
+                .map(|m| format!("{}({})", m.name, m.params.join(", ")))
+                .collect::<Vec<_>>();
+            println!("connectives: {}", macros.join(", "));
+        } else if vars.len() > TABLE_LIMIT {
+            println!("too many variables for a truth table ({})", vars.len());
+        } else {
+            print_table(&truth_table(&ast, order, self.inline)?);
+        }
 
-            ls2.push(new_table2);
+        for (name, expr) in shown {
+            let analysis = classify_in(&ast, expr)?;
+            let witnesses = [
+                ("true", analysis.satisfying),
+                ("false", analysis.falsifying),
+            ]
+            .into_iter()
+            .filter_map(|(value, w)| w.map(|w| format!("{value} for {}", assignment(&vars, &w))))
+            .collect::<Vec<_>>();
+            println!("{name}: {} ({})", analysis.class, witnesses.join("; "));
+        }
+        self.program = ast;
+        Ok(())
+    }
+}
+
+fn print_table(table: &TruthTable) {
+    let header = table_line(&table.vars, &table.names);
+    println!("{header}");
+    println!(
+        "{}",
+        header
+            .chars()
+            .map(|c| if c == '|' { '|' } else { '-' })
+            .collect::<String>()
+    );
+    for (row, results) in &table.rows {
+        println!(
+            "{}",
+            table_line(
+                &table
+                    .vars
+                    .iter()
+                    .map(|k| format!("{:>w$}", row[k] as i32, w = k.len()))
+                    .collect::<Vec<_>>(),
+                &table
+                    .names
+                    .iter()
+                    .zip(results)
+                    .map(|(k, r)| {
+                        let cell = r.map_or("X".to_string(), |r| (r as i32).to_string());
+                        format!("{cell:>w$}", w = k.len())
+                    })
+                    .collect::<Vec<_>>()
+            )
+        );
+    }
+}
+
+/// Prints the graphs of every definition of the program on stdin, the syntax trees
+/// or the decision diagrams if `bdd` is set
+fn print_dots(bdd: bool) {
+    let input = match io::read_to_string(io::stdin()) {
+        Ok(i) => i,
+        Err(e) => return eprintln!("error: {e}"),
+    };
+    let repl = Repl {
+        program: match parse_program(&input) {
+            Ok(p) => p,
+            Err(e) => return eprintln!("{}", e.report(&input)),
+        },
+        ..Repl::default()
+    };
+    for (name, expr) in &repl.program.definitions {
+        if bdd {
+            // the program was just parsed, so the definition exists
+            println!("{}", repl.bdd_dot(name).unwrap());
+        } else {
+            println!("{}", expr_dot(name, &repl.program.expand(expr)));
         }
-        tables = ls2;
     }
-    tables
 }
 
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
 
+    // `--dot` prints the graphs of a program instead of running the REPL
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => {}
+        ["--dot"] => return print_dots(false),
+        ["--dot", "bdd"] => return print_dots(true),
+        _ => return eprintln!("usage: logic_parser [--dot [bdd]]"),
+    }
+
     println!("Hello, world!");
 
     //parser_repl(expr);
+    let stdin = io::stdin();
+    let mut repl = Repl::default();
     loop {
         println!("##########");
-        let stdin = io::stdin();
-        let line1 = stdin.lock().lines().next().unwrap().unwrap();
-        let ast = parse(start, &line1).unwrap();
-        //println!("{:?}", ast.clone());
-
-        let (names, asts): (Vec<String>, Vec<Expr>) = ast.into_iter().unzip();
-        let vars = asts.iter().flat_map(|e| get_vars(e.clone())).collect::<HashSet<String>>();
-        let mut vars_sorted = vars.clone().into_iter().collect::<Vec<_>>();
-        vars_sorted.sort();
-
-        println!(
-            "| {} ||| {} |",
-            vars_sorted.join(" | "),
-            names.join(" | ")
-        );
-        for i in make_table(vars) {
-            println!("{:?}", i);
+        let Some(line1) = stdin.lock().lines().next() else {
+            break;
+        };
+        let line1 = match line1 {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("error: {e}");
+                break;
+            }
+        };
+        if line1.trim().is_empty() {
+            continue;
         }
-        /*
-        println!(
-            "| {} | result |",
-            ast.iter().map(|v| v.iter()
-                .map(|k| k.clone())
-                .collect::<Vec<String>>()
-                .join(" | ")
-        )
-                .collect::<Vec<String>>()
-                .join(" | ")
-            );
-        println!(
-            "|-{}-|--------|",
-            ast.iter()
-                .map(|k| "-".repeat(k.len()))
-                .collect::<Vec<String>>()
-                .join("-|-")
-        );
-        */
-        /*
-        let table = make_table(vars);
-        for t in table {
-            println!(
-                "| {} |      {} |",
-                t.keys()
-                    //.map(|k| format!("{:>w$}", b2i(*t.get(k).unwrap()), w = k.len()))
-                    .map(|k| format!("{:>w$}", *t.get(k).unwrap() as i32, w = k.len()))
-                    .collect::<Vec<_>>()
-                    .join(" | "),
-                interpret(ast.clone(), t) as i32
-            );
+        let res = repl.handle(&line1);
+        if let Err(e) = res {
+            eprintln!("{}", e.report(&line1));
         }
-        */
     }
 }
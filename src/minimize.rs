@@ -0,0 +1,344 @@
+use std::collections::HashSet;
+
+use crate::normal::build;
+use crate::{eval, make_table, EvalError, Expr, Literal};
+
+/// A product of literals, as the rows of the truth table it covers
+///
+/// Row numbers count in binary like [`make_table`], with the first variable as the
+/// highest bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Implicant {
+    /// The values of the variables kept in the product
+    pub value: usize,
+    /// The variables left out of the product
+    pub mask: usize,
+}
+
+impl Implicant {
+    pub fn covers(&self, row: usize) -> bool {
+        row & !self.mask == self.value
+    }
+
+    /// The literals of the product, in the order of `vars`
+    pub fn literals(&self, vars: &[String]) -> Vec<Literal> {
+        let n = vars.len();
+        vars.iter()
+            .enumerate()
+            .filter(|(i, _)| self.mask & 1 << (n - 1 - i) == 0)
+            .map(|(i, v)| (v.clone(), self.value & 1 << (n - 1 - i) != 0))
+            .collect()
+    }
+
+    /// The product as a row of `0`, `1` and `-` for the left out variables
+    pub fn pattern(&self, n: usize) -> String {
+        (0..n)
+            .rev()
+            .map(|i| match (self.mask >> i & 1, self.value >> i & 1) {
+                (1, _) => '-',
+                (_, 1) => '1',
+                _ => '0',
+            })
+            .collect()
+    }
+}
+
+/// The steps of minimising a formula into a sum of products, or a product of sums
+///
+/// A product of sums is found as the negation of the minimal sum of products of the
+/// negated formula, so its implicants are the ones of the negated formula.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimization {
+    pub vars: Vec<String>,
+    pub product_of_sums: bool,
+    /// The rows to cover, where the formula is true (false for a product of sums)
+    /// and which are not don't-cares
+    pub minterms: Vec<usize>,
+    /// The rows where the value of the formula does not matter
+    pub dont_cares: Vec<usize>,
+    pub primes: Vec<Implicant>,
+    /// The indices of the primes which alone cover some minterm
+    pub essential: Vec<usize>,
+    /// The indices of the primes in the smallest sum, essential ones included
+    pub cover: Vec<usize>,
+}
+
+impl Minimization {
+    /// The literals of the product (or the sum) for a prime
+    fn literals(&self, prime: usize) -> Vec<Literal> {
+        let mut literals = self.primes[prime].literals(&self.vars);
+        if self.product_of_sums {
+            literals.iter_mut().for_each(|(_, p)| *p = !*p);
+        }
+        literals
+    }
+
+    /// The product, or the sum, for a prime
+    pub fn term(&self, prime: usize) -> Expr {
+        build(&[self.literals(prime)], self.product_of_sums)
+    }
+
+    /// The minimal sum of products, or product of sums
+    pub fn expr(&self) -> Expr {
+        let terms = self
+            .cover
+            .iter()
+            .map(|&p| self.literals(p))
+            .collect::<Vec<_>>();
+        build(&terms, self.product_of_sums)
+    }
+}
+
+/// Finds a minimal sum of products for `expr` over `vars` with the Quine–McCluskey
+/// algorithm, choosing among the prime implicants with Petrick's method
+///
+/// The sum has as few products as possible, and of those as few literals. Rows where
+/// `dont_care` is true may be covered or not, whichever makes the sum smaller.
+pub fn minimize(
+    expr: &Expr,
+    dont_care: Option<&Expr>,
+    vars: &[String],
+) -> Result<Minimization, EvalError> {
+    minimize_(expr, dont_care, vars, false)
+}
+
+/// Finds a minimal product of sums for `expr` over `vars`, see [`minimize`]
+pub fn minimize_pos(
+    expr: &Expr,
+    dont_care: Option<&Expr>,
+    vars: &[String],
+) -> Result<Minimization, EvalError> {
+    minimize_(expr, dont_care, vars, true)
+}
+
+fn minimize_(
+    expr: &Expr,
+    dont_care: Option<&Expr>,
+    vars: &[String],
+    product_of_sums: bool,
+) -> Result<Minimization, EvalError> {
+    let mut minterms = vec![];
+    let mut dont_cares = vec![];
+    for (i, row) in make_table(vars).into_iter().enumerate() {
+        if let Some(dc) = dont_care {
+            if eval(dc, &row)? {
+                dont_cares.push(i);
+                continue;
+            }
+        }
+        if eval(expr, &row)? != product_of_sums {
+            minterms.push(i);
+        }
+    }
+    // the don't-cares can make primes bigger, but primes covering only them are useless
+    let primes = prime_implicants(vars.len(), &[minterms.clone(), dont_cares.clone()].concat())
+        .into_iter()
+        .filter(|p| minterms.iter().any(|m| p.covers(*m)))
+        .collect::<Vec<_>>();
+
+    // the primes covering each minterm
+    let chart = minterms
+        .iter()
+        .map(|&m| {
+            (0..primes.len())
+                .filter(|&p| primes[p].covers(m))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut essential = chart
+        .iter()
+        .filter(|ps| ps.len() == 1)
+        .map(|ps| ps[0])
+        .collect::<Vec<_>>();
+    essential.sort();
+    essential.dedup();
+
+    let mut rest = chart
+        .into_iter()
+        .filter(|ps| !ps.iter().any(|p| essential.contains(p)))
+        .collect::<Vec<_>>();
+    rest.sort();
+    rest.dedup();
+    let size = |p: &usize| vars.len() - primes[*p].mask.count_ones() as usize;
+    let mut cover = petrick(&rest, size);
+    cover.extend(&essential);
+    cover.sort();
+
+    Ok(Minimization {
+        vars: vars.to_vec(),
+        product_of_sums,
+        minterms,
+        dont_cares,
+        primes,
+        essential,
+        cover,
+    })
+}
+
+/// All the prime implicants of the function true on `minterms`, by repeatedly merging
+/// products differing in a single variable
+fn prime_implicants(n: usize, minterms: &[usize]) -> Vec<Implicant> {
+    let mut primes = vec![];
+    let mut level = minterms
+        .iter()
+        .map(|&m| Implicant { value: m, mask: 0 })
+        .collect::<HashSet<_>>();
+    while !level.is_empty() {
+        let mut next = HashSet::new();
+        let mut merged = HashSet::new();
+        for imp in &level {
+            for bit in (0..n).map(|i| 1 << i).filter(|b| imp.mask & b == 0) {
+                let other = Implicant {
+                    value: imp.value ^ bit,
+                    mask: imp.mask,
+                };
+                if level.contains(&other) {
+                    merged.insert(*imp);
+                    next.insert(Implicant {
+                        value: imp.value & !bit,
+                        mask: imp.mask | bit,
+                    });
+                }
+            }
+        }
+        primes.extend(level.difference(&merged));
+        level = next;
+    }
+    primes.sort();
+    primes
+}
+
+/// Picks the cheapest set of primes covering every row of the chart, by multiplying out
+/// the product of its rows
+///
+/// This is exponential in the worst case, but the essential primes are usually
+/// taken out first.
+fn petrick(chart: &[Vec<usize>], size: impl Fn(&usize) -> usize) -> Vec<usize> {
+    // the sum of products so far, each one a sorted set of primes
+    let mut sums: Vec<Vec<usize>> = vec![vec![]];
+    for row in chart {
+        let mut next: Vec<Vec<usize>> = vec![];
+        for product in &sums {
+            for p in row {
+                let mut product = product.clone();
+                if let Err(i) = product.binary_search(p) {
+                    product.insert(i, *p);
+                }
+                next.push(product);
+            }
+        }
+        // x + xy = x
+        next.sort_by_key(|p| p.len());
+        let mut absorbed: Vec<Vec<usize>> = vec![];
+        for product in next {
+            if !absorbed
+                .iter()
+                .any(|a| a.iter().all(|p| product.contains(p)))
+            {
+                absorbed.push(product);
+            }
+        }
+        sums = absorbed;
+    }
+    sums.into_iter()
+        .min_by_key(|ps| (ps.len(), ps.iter().map(&size).sum::<usize>()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_expr;
+
+    fn vars() -> Vec<String> {
+        ["a", "b", "c"].map(String::from).to_vec()
+    }
+
+    #[test]
+    fn implicates_are_sums() {
+        let expr = parse_expr("(a | b) & (!a | c)").unwrap();
+        let min = minimize_pos(&expr, None, &vars()).unwrap();
+        assert!(!min.primes.is_empty());
+        for i in 0..min.primes.len() {
+            let term = min.term(i);
+            assert!(matches!(term, Expr::Or(..)), "{term}");
+            // every implicate is true wherever the formula is
+            for row in make_table(&vars()) {
+                assert!(!eval(&expr, &row).unwrap() || eval(&term, &row).unwrap());
+            }
+        }
+        for row in make_table(&vars()) {
+            assert_eq!(eval(&min.expr(), &row).unwrap(), eval(&expr, &row).unwrap());
+        }
+    }
+
+    #[test]
+    fn dont_cares_may_be_covered() {
+        let expr = parse_expr("a & b").unwrap();
+        let dc = parse_expr("a & !b").unwrap();
+        let min = minimize(&expr, Some(&dc), &vars()).unwrap();
+        assert_eq!(min.expr().to_string(), "a");
+        // a product of sums can use them as well
+        let min = minimize_pos(&expr, Some(&dc), &vars()).unwrap();
+        assert_eq!(min.expr().to_string(), "a");
+    }
+
+    #[test]
+    fn implicants_are_products() {
+        let expr = parse_expr("a & b | !a & c").unwrap();
+        let min = minimize(&expr, None, &vars()).unwrap();
+        for i in 0..min.primes.len() {
+            assert!(matches!(min.term(i), Expr::And(..)), "{}", min.term(i));
+        }
+        assert_eq!(min.expr().to_string(), "!a & c | a & b");
+    }
+
+    /// Whether the primes `chosen` cover every minterm of `min`
+    fn covers_all(min: &Minimization, chosen: &[usize]) -> bool {
+        min.minterms
+            .iter()
+            .all(|m| chosen.iter().any(|p| min.primes[*p].covers(*m)))
+    }
+
+    #[test]
+    fn cyclic_cores_need_petrick() {
+        // every minterm is covered by two primes, so none is essential
+        let expr = parse_expr("!a & b | !b & c | !c & a").unwrap();
+        let min = minimize(&expr, None, &vars()).unwrap();
+        assert_eq!(min.primes.len(), 6);
+        assert!(min.essential.is_empty());
+        assert_eq!(min.cover.len(), 3);
+        assert!(covers_all(&min, &min.cover));
+        for row in make_table(&vars()) {
+            assert_eq!(eval(&min.expr(), &row).unwrap(), eval(&expr, &row).unwrap());
+        }
+    }
+
+    #[test]
+    fn covers_are_minimal() {
+        let vars = ["a", "b", "c", "d"].map(String::from);
+        let mut rng = crate::testing::Rng(0x5851_f42d_4c95_7f2d);
+        for _ in 0..100 {
+            let expr = rng.formula(4, 5);
+            let min = minimize(&expr, None, &vars).unwrap();
+            assert!(covers_all(&min, &min.cover), "{expr}");
+            for e in &min.essential {
+                assert!(min.cover.contains(e), "{expr}");
+            }
+            // no smaller set of primes covers the minterms
+            let n = min.primes.len();
+            if n > 16 {
+                continue;
+            }
+            let smallest = (0..1u32 << n)
+                .filter(|set| {
+                    let chosen = (0..n).filter(|p| set >> p & 1 == 1).collect::<Vec<_>>();
+                    covers_all(&min, &chosen)
+                })
+                .map(u32::count_ones)
+                .min()
+                .unwrap();
+            assert_eq!(min.cover.len(), smallest as usize, "{expr}");
+        }
+    }
+}
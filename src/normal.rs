@@ -0,0 +1,357 @@
+use crate::{eval, make_table, EvalError, Expr};
+
+/// A variable with its polarity, `true` for the variable itself and `false` for its negation
+pub type Literal = (String, bool);
+
+/// Rewrites `a => b` as `!a | b`, `a <=> b` as `(!a | b) & (a | !b)` and the other
+/// connectives likewise with only `!`, `&` and `|`, and calls of connectives as their bodies
+pub fn eliminate(expr: &Expr) -> Expr {
+    match expr {
+        Expr::True | Expr::False | Expr::Term(_) => expr.clone(),
+        Expr::Not(e) => Expr::Not(eliminate(e).into()),
+        Expr::And(l, r) => Expr::And(eliminate(l).into(), eliminate(r).into()),
+        Expr::Or(l, r) => Expr::Or(eliminate(l).into(), eliminate(r).into()),
+        Expr::Imply(l, r) => Expr::Or(Expr::Not(eliminate(l).into()).into(), eliminate(r).into()),
+        Expr::Equiv(l, r) => {
+            let (l, r) = (eliminate(l), eliminate(r));
+            Expr::And(
+                Expr::Or(Expr::Not(l.clone().into()).into(), r.clone().into()).into(),
+                Expr::Or(l.into(), Expr::Not(r.into()).into()).into(),
+            )
+        }
+        Expr::Xor(l, r) => {
+            let (l, r) = (eliminate(l), eliminate(r));
+            Expr::And(
+                Expr::Or(l.clone().into(), r.clone().into()).into(),
+                Expr::Or(Expr::Not(l.into()).into(), Expr::Not(r.into()).into()).into(),
+            )
+        }
+        Expr::Nand(l, r) => Expr::Not(Expr::And(eliminate(l).into(), eliminate(r).into()).into()),
+        Expr::Nor(l, r) => Expr::Not(Expr::Or(eliminate(l).into(), eliminate(r).into()).into()),
+        Expr::Converse(l, r) => {
+            Expr::Or(eliminate(l).into(), Expr::Not(eliminate(r).into()).into())
+        }
+        Expr::Apply(m, args) => eliminate(&m.apply(args)),
+    }
+}
+
+/// Negation normal form: only `&`, `|` and negated variables, negated constants are flipped
+pub fn nnf(expr: &Expr) -> Expr {
+    nnf_(expr, false)
+}
+
+fn nnf_(expr: &Expr, negate: bool) -> Expr {
+    let and = |l, r| Expr::And(Box::new(l), Box::new(r));
+    let or = |l, r| Expr::Or(Box::new(l), Box::new(r));
+    match (expr, negate) {
+        (Expr::True, false) | (Expr::False, true) => Expr::True,
+        (Expr::True, true) | (Expr::False, false) => Expr::False,
+        (Expr::Term(_), false) => expr.clone(),
+        (Expr::Term(_), true) => Expr::Not(expr.clone().into()),
+        (Expr::Not(e), _) => nnf_(e, !negate),
+        (Expr::And(l, r), false) => and(nnf_(l, false), nnf_(r, false)),
+        (Expr::And(l, r), true) => or(nnf_(l, true), nnf_(r, true)),
+        (Expr::Or(l, r), false) => or(nnf_(l, false), nnf_(r, false)),
+        (Expr::Or(l, r), true) => and(nnf_(l, true), nnf_(r, true)),
+        (Expr::Imply(l, r), false) => or(nnf_(l, true), nnf_(r, false)),
+        (Expr::Imply(l, r), true) => and(nnf_(l, false), nnf_(r, true)),
+        (Expr::Equiv(l, r), false) => and(
+            or(nnf_(l, true), nnf_(r, false)),
+            or(nnf_(l, false), nnf_(r, true)),
+        ),
+        (Expr::Equiv(l, r), true) => or(
+            and(nnf_(l, false), nnf_(r, true)),
+            and(nnf_(l, true), nnf_(r, false)),
+        ),
+        (Expr::Xor(l, r), false) => and(
+            or(nnf_(l, false), nnf_(r, false)),
+            or(nnf_(l, true), nnf_(r, true)),
+        ),
+        (Expr::Xor(l, r), true) => and(
+            or(nnf_(l, true), nnf_(r, false)),
+            or(nnf_(l, false), nnf_(r, true)),
+        ),
+        (Expr::Nand(l, r), false) => or(nnf_(l, true), nnf_(r, true)),
+        (Expr::Nand(l, r), true) => and(nnf_(l, false), nnf_(r, false)),
+        (Expr::Nor(l, r), false) => and(nnf_(l, true), nnf_(r, true)),
+        (Expr::Nor(l, r), true) => or(nnf_(l, false), nnf_(r, false)),
+        (Expr::Converse(l, r), false) => or(nnf_(l, false), nnf_(r, true)),
+        (Expr::Converse(l, r), true) => and(nnf_(l, true), nnf_(r, false)),
+        (Expr::Apply(m, args), _) => nnf_(&m.apply(args), negate),
+    }
+}
+
+/// The clauses of the conjunctive normal form of `expr`
+///
+/// Built by distributing `|` over `&`, which can be exponential in the size of `expr`.
+/// Clauses never contain a variable twice, ones containing both polarities are dropped.
+pub fn cnf_clauses(expr: &Expr) -> Vec<Vec<Literal>> {
+    collect(&nnf(expr), true)
+}
+
+/// The terms of the disjunctive normal form of `expr`, see [`cnf_clauses`]
+pub fn dnf_terms(expr: &Expr) -> Vec<Vec<Literal>> {
+    collect(&nnf(expr), false)
+}
+
+/// A conjunction of disjunctions equivalent to `expr`
+pub fn cnf(expr: &Expr) -> Expr {
+    build(&cnf_clauses(expr), true)
+}
+
+/// A disjunction of conjunctions equivalent to `expr`
+pub fn dnf(expr: &Expr) -> Expr {
+    build(&dnf_terms(expr), false)
+}
+
+/// The product of maxterms over `vars`, one for every row of the truth table where `expr`
+/// is false
+pub fn canonical_cnf(expr: &Expr, vars: &[String]) -> Result<Expr, EvalError> {
+    let mut clauses = Vec::new();
+    for row in make_table(vars) {
+        if !eval(expr, &row)? {
+            clauses.push(vars.iter().map(|v| (v.clone(), !row[v])).collect());
+        }
+    }
+    Ok(build(&clauses, true))
+}
+
+/// The sum of minterms over `vars`, one for every row of the truth table where `expr`
+/// is true
+pub fn canonical_dnf(expr: &Expr, vars: &[String]) -> Result<Expr, EvalError> {
+    let mut terms = Vec::new();
+    for row in make_table(vars) {
+        if eval(expr, &row)? {
+            terms.push(vars.iter().map(|v| (v.clone(), row[v])).collect());
+        }
+    }
+    Ok(build(&terms, false))
+}
+
+/// Collects the clauses (`cnf`) or terms of an expression in negation normal form
+fn collect(expr: &Expr, cnf: bool) -> Vec<Vec<Literal>> {
+    match expr {
+        Expr::True if cnf => vec![],
+        Expr::False if !cnf => vec![],
+        Expr::True | Expr::False => vec![vec![]],
+        Expr::Term(v) => vec![vec![(v.clone(), true)]],
+        Expr::Not(e) => match &**e {
+            Expr::Term(v) => vec![vec![(v.clone(), false)]],
+            _ => unreachable!("not in negation normal form"),
+        },
+        // the outer connective, the clauses of both sides are kept unless one is empty
+        Expr::And(l, r) | Expr::Or(l, r) if matches!(expr, Expr::And(..)) == cnf => {
+            let clauses = [collect(l, cnf), collect(r, cnf)].concat();
+            if clauses.iter().any(|c| c.is_empty()) {
+                return vec![vec![]];
+            }
+            clauses
+        }
+        // the inner connective, every clause of one side is merged with every one of the other
+        Expr::And(l, r) | Expr::Or(l, r) => {
+            let right = collect(r, cnf);
+            let mut clauses = Vec::new();
+            for a in collect(l, cnf) {
+                for b in &right {
+                    if let Some(c) = merge(&a, b) {
+                        if !clauses.contains(&c) {
+                            clauses.push(c);
+                        }
+                    }
+                }
+            }
+            clauses
+        }
+        Expr::Imply(..)
+        | Expr::Equiv(..)
+        | Expr::Xor(..)
+        | Expr::Nand(..)
+        | Expr::Nor(..)
+        | Expr::Converse(..)
+        | Expr::Apply(..) => unreachable!("not in negation normal form"),
+    }
+}
+
+/// The union of two clauses, `None` if it contains a variable in both polarities
+fn merge(a: &[Literal], b: &[Literal]) -> Option<Vec<Literal>> {
+    let mut merged = a.to_vec();
+    for (v, p) in b {
+        match merged.iter().find(|(w, _)| w == v) {
+            Some((_, q)) if q != p => return None,
+            Some(_) => {}
+            None => merged.push((v.clone(), *p)),
+        }
+    }
+    Some(merged)
+}
+
+/// Turns clauses (`cnf`) or terms back into an expression
+pub(crate) fn build(clauses: &[Vec<Literal>], cnf: bool) -> Expr {
+    type Connective = fn(Box<Expr>, Box<Expr>) -> Expr;
+    let (outer, inner): (Connective, Connective) = if cnf {
+        (Expr::And, Expr::Or)
+    } else {
+        (Expr::Or, Expr::And)
+    };
+    let literal = |(v, p): &Literal| {
+        let term = Expr::Term(v.clone());
+        if *p {
+            term
+        } else {
+            Expr::Not(term.into())
+        }
+    };
+    let clause = |c: &Vec<Literal>| {
+        c.iter()
+            .map(literal)
+            .reduce(|a, b| inner(Box::new(a), Box::new(b)))
+            // the empty clause is false, the empty term is true
+            .unwrap_or(if cnf { Expr::False } else { Expr::True })
+    };
+    clauses
+        .iter()
+        .map(clause)
+        .reduce(|a, b| outer(Box::new(a), Box::new(b)))
+        .unwrap_or(if cnf { Expr::True } else { Expr::False })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+    use crate::{get_vars, parse_expr, parse_program};
+
+    fn formulas() -> Vec<Expr> {
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        let mut exprs = (0..200).map(|_| rng.formula(4, 5)).collect::<Vec<_>>();
+        exprs.extend(
+            [
+                "a & true",
+                "!(a | false) => b",
+                "!!a <=> !(b ^ true)",
+                "a nor !a",
+            ]
+            .map(|f| parse_expr(f).unwrap()),
+        );
+        let program = parse_program("maj(x, y, z) = x & y | y & z | x & z; f = !maj(a, b, !c);");
+        exprs.push(program.unwrap().definitions[0].1.clone());
+        exprs
+    }
+
+    fn sorted_vars(expr: &Expr) -> Vec<String> {
+        let mut vars = get_vars(expr).into_iter().collect::<Vec<_>>();
+        vars.sort();
+        vars
+    }
+
+    fn equivalent(a: &Expr, b: &Expr, vars: &[String]) -> bool {
+        make_table(vars)
+            .iter()
+            .all(|row| eval(a, row).unwrap() == eval(b, row).unwrap())
+    }
+
+    fn is_literal(expr: &Expr) -> bool {
+        match expr {
+            Expr::Term(_) => true,
+            Expr::Not(e) => matches!(**e, Expr::Term(_)),
+            _ => false,
+        }
+    }
+
+    fn is_nnf(expr: &Expr) -> bool {
+        match expr {
+            Expr::True | Expr::False => true,
+            Expr::And(l, r) | Expr::Or(l, r) => is_nnf(l) && is_nnf(r),
+            _ => is_literal(expr),
+        }
+    }
+
+    /// Whether `expr` is made of expressions satisfying `part` joined by the connective of `and`
+    fn joined(expr: &Expr, and: bool, part: &dyn Fn(&Expr) -> bool) -> bool {
+        match expr {
+            Expr::And(l, r) if and => joined(l, and, part) && joined(r, and, part),
+            Expr::Or(l, r) if !and => joined(l, and, part) && joined(r, and, part),
+            _ => part(expr),
+        }
+    }
+
+    fn is_cnf(expr: &Expr) -> bool {
+        matches!(expr, Expr::True | Expr::False)
+            || joined(expr, true, &|c| joined(c, false, &is_literal))
+    }
+
+    fn is_dnf(expr: &Expr) -> bool {
+        matches!(expr, Expr::True | Expr::False)
+            || joined(expr, false, &|t| joined(t, true, &is_literal))
+    }
+
+    #[test]
+    fn forms_are_equivalent() {
+        for expr in formulas() {
+            let vars = sorted_vars(&expr);
+            let forms = [
+                eliminate(&expr),
+                nnf(&expr),
+                cnf(&expr),
+                dnf(&expr),
+                canonical_cnf(&expr, &vars).unwrap(),
+                canonical_dnf(&expr, &vars).unwrap(),
+            ];
+            for form in forms {
+                assert!(equivalent(&expr, &form, &vars), "{expr} vs {form}");
+            }
+        }
+    }
+
+    #[test]
+    fn forms_have_their_shape() {
+        for expr in formulas() {
+            let vars = sorted_vars(&expr);
+            assert!(is_nnf(&nnf(&expr)), "{}", nnf(&expr));
+            assert!(is_cnf(&cnf(&expr)), "{}", cnf(&expr));
+            assert!(is_dnf(&dnf(&expr)), "{}", dnf(&expr));
+            let canonical = canonical_cnf(&expr, &vars).unwrap();
+            assert!(is_cnf(&canonical), "{canonical}");
+            let canonical = canonical_dnf(&expr, &vars).unwrap();
+            assert!(is_dnf(&canonical), "{canonical}");
+        }
+        assert!(!is_cnf(&parse_expr("a | b & c").unwrap()));
+        assert!(!is_nnf(&parse_expr("!(a & b)").unwrap()));
+        let xor = parse_expr("a ^ b").unwrap();
+        let vars = sorted_vars(&xor);
+        let canonical = canonical_dnf(&xor, &vars).unwrap();
+        assert_eq!(canonical.to_string(), "!a & b | a & !b");
+        let canonical = canonical_cnf(&xor, &vars).unwrap();
+        assert_eq!(canonical.to_string(), "(a | b) & (!a | !b)");
+    }
+
+    #[test]
+    fn canonical_forms_are_unique() {
+        let vars = ["a", "b", "c"].map(String::from);
+        let same = [
+            "a => b & c",
+            "!(b & c) => !a",
+            "!a | b & c",
+            "(a => b) & (c | !a)",
+        ]
+        .map(|f| parse_expr(f).unwrap());
+        for f in &same {
+            assert_eq!(
+                canonical_cnf(f, &vars).unwrap(),
+                canonical_cnf(&same[0], &vars).unwrap()
+            );
+            assert_eq!(
+                canonical_dnf(f, &vars).unwrap(),
+                canonical_dnf(&same[0], &vars).unwrap()
+            );
+        }
+        // the normal forms of a formula have the same canonical forms as it
+        for expr in formulas() {
+            let vars = sorted_vars(&expr);
+            let canonical = canonical_dnf(&expr, &vars).unwrap();
+            for form in [cnf(&expr), dnf(&expr), canonical_cnf(&expr, &vars).unwrap()] {
+                assert_eq!(canonical_dnf(&form, &vars).unwrap(), canonical, "{expr}");
+            }
+        }
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use untwine::{parser, prelude::ParserContext, ParserError};
+
+use crate::{EvalError, Expr, Macro, Program};
+
+/// Side data collected while parsing
+#[derive(Debug, Default)]
+pub(crate) struct ParseState {
+    /// Errors the parser recovered from, so that it can report the most relevant one
+    errors: Vec<(Range<usize>, String)>,
+    /// Spans of the names of the definitions parsed so far
+    names: Vec<(String, Range<usize>)>,
+    /// The definitions given a don't-care set so far
+    dont_cares: Vec<String>,
+    /// Spans of the variables read since the last definition
+    terms: Vec<(String, Range<usize>)>,
+    /// The connectives declared so far, which can be called from then on
+    macros: Vec<Macro>,
+}
+
+/// The words with a meaning of their own, which cannot name variables or definitions
+const RESERVED: [&str; 11] = [
+    "true", "false", "not", "and", "nand", "xor", "or", "nor", "implies", "iff", "xnor",
+];
+
+/// The reserved word `name` is made of, if any, so that `true[1]` is not a variable either
+fn reserved(name: &str) -> Option<&str> {
+    let word = name.split('[').next().unwrap_or(name);
+    RESERVED.contains(&word).then_some(word)
+}
+
+fn ident_start(c: &char) -> bool {
+    c.is_ascii_alphabetic() || *c == '_'
+}
+
+fn ident_char(c: &char) -> bool {
+    c.is_ascii_alphanumeric() || *c == '_'
+}
+
+/// A definition or a don't-care set as parsed
+struct Parsed {
+    dont_care: bool,
+    name: String,
+    /// The parameters of a connective like `maj(x, y, z) = ...;`
+    params: Option<Vec<String>>,
+    expr: Expr,
+    /// Spans of the variables in `expr`
+    terms: Vec<(String, Range<usize>)>,
+}
+
+impl ParseState {
+    fn error(&mut self, span: Range<usize>, message: String) {
+        self.errors.push((span, message));
+    }
+
+    /// Reports a missing operand after `op`, using `false` in its place
+    fn operand(&mut self, e: Option<Expr>, at: usize, op: &str) -> Expr {
+        e.unwrap_or_else(|| {
+            self.error(at..at, format!("expected expression after `{op}`"));
+            Expr::False
+        })
+    }
+}
+
+// the rules are lenient where the input is commonly wrong (missing operands,
+// unclosed parens, missing `;`...) and record an error instead of failing,
+// so that the error points at the actual problem and not wherever untwine gave up
+parser! {
+    [data = ParseState, context = ctx]
+    w = #["\n\r\t "]*;
+    pos: "" -> usize { ctx.cursor() }
+    // names like `x1`, `req_valid` and indexed ones like `a[3]` or `m[0][2]`
+    ident = <{ident_start} {ident_char}* ("[" {char::is_ascii_digit}+ "]")*> -> &str;
+
+    term: p=pos term=ident -> Expr {
+        match term {
+            "true" => Expr::True,
+            "false" => Expr::False,
+            t => {
+                let mut state = ctx.data_mut();
+                if let Some(word) = reserved(t) {
+                    state.error(p..p + t.len(), format!("`{word}` is a reserved word, it cannot be a variable"));
+                } else if state.macros.iter().any(|m| m.name == t) {
+                    state.error(p..p + t.len(), format!("`{t}` is a function, call it like `{t}(...)`"));
+                }
+                state.terms.push((t.to_string(), p..p + t.len()));
+                Expr::Term(t.to_string())
+            }
+        }
+    }
+
+    // calls of the connectives declared before, like `maj(a, b, c)`
+    call: p=pos name=ident w "(" args=expr$","* close=<")"?> -> Expr {
+        let mut state = ctx.data_mut();
+        let span = p..p + name.len();
+        match state.macros.iter().find(|m| m.name == name).cloned() {
+            // the number of arguments is only known once they are all there
+            _ if close.is_empty() => {
+                let at = ctx.cursor();
+                state.error(at..at, format!("expected `,` or `)` after the arguments of `{name}`"));
+                Expr::False
+            }
+            None => {
+                state.error(span, format!("unknown function `{name}`"));
+                Expr::False
+            }
+            Some(m) if m.params.len() != args.len() => {
+                let message = format!(
+                    "`{name}` takes {} argument{}, got {}",
+                    m.params.len(),
+                    if m.params.len() == 1 { "" } else { "s" },
+                    args.len()
+                );
+                state.error(span, message);
+                Expr::False
+            }
+            Some(m) => Expr::Apply(m.into(), args),
+        }
+    }
+
+    // `⊤` and `1` are `true`, `⊥` and `0` are `false`
+    constant: c=<(["⊤⊥"] | "1" word_end | "0" word_end)> -> Expr {
+        match c {
+            "⊤" | "1" => Expr::True,
+            _ => Expr::False,
+        }
+    }
+
+    // every operator can also be written as in logic textbooks or in C, or spelled out;
+    // the symbols are matched as character groups, untwine only matches ASCII literals
+    negation: op=<("!" | ["¬"] | "~" | "not" word_end)> w p=pos e=unary? -> Expr {
+        Expr::Not(ctx.data_mut().operand(e, p, op).into())
+    }
+    paren: p=pos "(" w q=pos e=expr? close=<")"?> -> Expr {
+        let mut state = ctx.data_mut();
+        if close.is_empty() {
+            state.error(p..p + 1, "unbalanced `(`".to_string());
+        }
+        state.operand(e, q, "(")
+    }
+    unary = w (negation | call | term | constant | paren) w -> Expr;
+
+    // the end of a word operator like `xor`, so that `xorb` is still a variable
+    word_end = #[not] {|c: &char| ident_char(c) || *c == '['};
+
+    // precedence from the tightest: ! (& nand) (^ xor !=) (| nor) => <= (<=> xnor)
+    // everything is left-associative except for =>
+    and: first=unary rest=(<("&&" | "&" | ["∧"] | "and" word_end | "nand" word_end)> w pos unary?)*
+        -> Expr
+    {
+        rest.into_iter().fold(first, |l, (op, p, r)| {
+            let r = ctx.data_mut().operand(r, p, op).into();
+            match op {
+                "nand" => Expr::Nand(l.into(), r),
+                _ => Expr::And(l.into(), r),
+            }
+        })
+    }
+    xor: first=and rest=(<("^" | "!=" | ["⊕"] | "xor" word_end)> w pos and?)* -> Expr {
+        rest.into_iter().fold(first, |l, (op, p, r)| {
+            Expr::Xor(l.into(), ctx.data_mut().operand(r, p, op).into())
+        })
+    }
+    or: first=xor rest=(<("||" | "|" | ["∨"] | "or" word_end | "nor" word_end)> w pos xor?)* -> Expr {
+        rest.into_iter().fold(first, |l, (op, p, r)| {
+            let r = ctx.data_mut().operand(r, p, op).into();
+            match op {
+                "nor" => Expr::Nor(l.into(), r),
+                _ => Expr::Or(l.into(), r),
+            }
+        })
+    }
+    imply: left=or right=(<("=>" | ["→⇒⟹"] | "implies" word_end)> w pos imply?)? -> Expr {
+        match right {
+            Some((op, p, r)) => Expr::Imply(left.into(), ctx.data_mut().operand(r, p, op).into()),
+            None => left,
+        }
+    }
+    // `<=` is not the start of `<=>`
+    converse: first=imply rest=("<=" #[not] ">" w pos imply?)* -> Expr {
+        rest.into_iter().fold(first, |l, (p, r)| {
+            Expr::Converse(l.into(), ctx.data_mut().operand(r, p, "<=").into())
+        })
+    }
+    equiv: first=converse rest=(<("<=>" | ["↔⇔⟺"] | "iff" word_end | "xnor" word_end)> w pos converse?)* -> Expr {
+        rest.into_iter().fold(first, |l, (op, p, r)| {
+            Expr::Equiv(l.into(), ctx.data_mut().operand(r, p, op).into())
+        })
+    }
+
+    pub expr = w equiv w -> Expr;
+    // `dc f = ...;` gives the don't-care set of `f`, which has to be defined before,
+    // and `maj(x, y, z) = ...;` declares a connective
+    definition: start=pos first=ident second=(w pos ident)? params=(w "(" w (pos ident)$(w "," w)* w ")")?
+        eq=<(w "=")?> w p=pos e=expr? semi=<";"?> w -> Parsed
+    {
+        let mut state = ctx.data_mut();
+        let terms = std::mem::take(&mut state.terms);
+        let (dc, name, name_span) = match second {
+            Some((at, name)) if first == "dc" => (true, name, at..at + name.len()),
+            Some((at, _)) => {
+                state.error(at..at, format!("expected `=` after `{first}`"));
+                (false, first, start..start + first.len())
+            }
+            None => (false, first, start..start + first.len()),
+        };
+        if let Some(word) = reserved(name) {
+            state.error(name_span.clone(), format!("`{word}` is a reserved word, it cannot be defined"));
+        }
+        let defined = state.names.iter().any(|(n, _)| n == name)
+            || state.macros.iter().any(|m| m.name == name);
+        if dc && params.is_some() {
+            state.error(name_span, "don't-care sets do not have parameters".to_string());
+        } else if dc {
+            if !state.names.iter().any(|(n, _)| n == name) {
+                state.error(name_span, format!("`{name}` has to be defined before its don't-care set"));
+            } else if state.dont_cares.iter().any(|n| n == name) {
+                state.error(name_span, format!("`{name}` already has a don't-care set"));
+            } else {
+                state.dont_cares.push(name.to_string());
+            }
+        } else if let Some(params) = &params {
+            if defined {
+                state.error(name_span, format!("`{name}` is already defined"));
+            }
+            for (i, (at, param)) in params.iter().enumerate() {
+                if let Some(word) = reserved(param) {
+                    state.error(*at..at + param.len(), format!("`{word}` is a reserved word, it cannot be a parameter"));
+                } else if params[..i].iter().any(|(_, q)| q == param) {
+                    state.error(*at..at + param.len(), format!("`{param}` is already a parameter of `{name}`"));
+                }
+            }
+            for (t, span) in &terms {
+                if !params.iter().any(|(_, q)| q == t) {
+                    state.error(span.clone(), format!("`{t}` is not a parameter of `{name}`"));
+                }
+            }
+            if let Some(body) = &e {
+                state.macros.push(Macro {
+                    name: name.to_string(),
+                    params: params.iter().map(|(_, q)| q.to_string()).collect(),
+                    body: body.clone(),
+                });
+            }
+        } else {
+            if defined {
+                state.error(name_span.clone(), format!("`{name}` is already defined"));
+            }
+            state.names.push((name.to_string(), name_span));
+        }
+        if eq.is_empty() {
+            state.error(p..p, format!("expected `=` after `{name}`"));
+        }
+        if e.is_none() {
+            state.error(p..p, format!("expected expression for `{name}`"));
+        } else if semi.is_empty() {
+            let at = ctx.cursor();
+            if ctx.slice().starts_with(')') {
+                state.error(at..at + 1, "unbalanced `)`".to_string());
+            } else if ctx.slice().starts_with('[') {
+                state.error(at..at + 1, "indices are numbers in brackets, like `a[3]`".to_string());
+            } else {
+                state.error(at..at, format!("expected `;` after expression for `{name}`"));
+            }
+        }
+        Parsed {
+            dont_care: dc,
+            name: name.to_string(),
+            params: params.map(|ps| ps.into_iter().map(|(_, q)| q.to_string()).collect()),
+            expr: e.unwrap_or(Expr::False),
+            terms,
+        }
+    }
+    pub start = w definition* -> Vec<Parsed>;
+}
+
+/// Runs `parser` on the whole `input`, turning the earliest error into an [`EvalError`]
+fn parse_all<T>(
+    input: &str,
+    parser: impl for<'p> Fn(&'p ParserContext<'p, ParseState, ParserError>) -> Option<T>,
+    expected: &str,
+) -> Result<T, EvalError> {
+    let mut ctx = ParserContext::new(input, ParseState::default());
+    let ast = parser(&ctx);
+    let end = ctx.cursor();
+    let ast = ctx.result(ast);
+
+    let mut errors = std::mem::take(&mut ctx.data_mut().errors);
+    if ast.is_err() {
+        // whatever is left could not be read at all
+        let len = input[end..].chars().next().map_or(0, char::len_utf8);
+        let message = if input[end..].starts_with(')') {
+            "unbalanced `)`".to_string()
+        } else {
+            format!("expected {expected}")
+        };
+        errors.push((end..end + len, message));
+    }
+    // the earliest error is the one the rest most likely follow from
+    if let Some((span, message)) = errors.into_iter().min_by_key(|(span, _)| span.start) {
+        return Err(EvalError::Parse { span, message });
+    }
+    Ok(ast.expect("parsing can only fail with an error"))
+}
+
+/// Parses a list of definitions like `f = a & b; g = !f;`, each optionally followed
+/// by a don't-care set like `dc f = a & !b;`
+///
+/// Definitions can use the ones before or after them, but not themselves. Connectives
+/// declared like `maj(x, y, z) = x & y | y & z | x & z;` can be called in everything
+/// after them.
+pub fn parse_program(input: &str) -> Result<Program, EvalError> {
+    let ast = parse_all(input, start, "a definition like `name = expression;`")?;
+    if ast.is_empty() {
+        return Err(EvalError::EmptyInput);
+    }
+    let mut program = Program::default();
+    let mut terms = HashMap::new();
+    for parsed in ast {
+        if let Some(params) = parsed.params {
+            program.macros.push(Macro {
+                name: parsed.name,
+                params,
+                body: parsed.expr,
+            });
+        } else if parsed.dont_care {
+            program.dont_cares.insert(parsed.name, parsed.expr);
+        } else {
+            terms.insert(parsed.name.clone(), parsed.terms);
+            program.definitions.push((parsed.name, parsed.expr));
+        }
+    }
+    if let Err(EvalError::RecursiveDefinition(cycle)) = program.dependency_order() {
+        // points at the use of the next definition in the first one of the cycle
+        let span = terms[&cycle[0]]
+            .iter()
+            .find(|(n, _)| *n == cycle[1])
+            .map(|(_, span)| span.clone())
+            .expect("the cycle comes from the definitions");
+        let message = if cycle.len() == 2 {
+            format!("`{}` is defined in terms of itself", cycle[0])
+        } else {
+            format!(
+                "`{}` is defined in terms of itself ({})",
+                cycle[0],
+                cycle.join(" -> ")
+            )
+        };
+        return Err(EvalError::Parse { span, message });
+    }
+    Ok(program)
+}
+
+/// Parses a single expression like `a & b => c`
+pub fn parse_expr(input: &str) -> Result<Expr, EvalError> {
+    if input.trim().is_empty() {
+        return Err(EvalError::EmptyInput);
+    }
+    parse_all(input, expr, "an operator")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(a: &str, b: &str) {
+        assert_eq!(parse_expr(a).unwrap(), parse_expr(b).unwrap(), "{a} vs {b}");
+    }
+
+    #[test]
+    fn precedence() {
+        same("a | b & c", "a | (b & c)");
+        same("a & b | c", "(a & b) | c");
+        same("!a & b", "(!a) & b");
+        same("!a | b", "(!a) | b");
+        same("a | b => c", "(a | b) => c");
+        same("a ^ b & c | d", "(a ^ (b & c)) | d");
+        same("a nand b ^ c nor d", "((a nand b) ^ c) nor d");
+        same("a => b <= c", "(a => b) <= c");
+        // `<=>` binds loosest
+        same("a | b <=> c & d => e", "(a | b) <=> ((c & d) => e)");
+        same("a <= b <=> c", "(a <= b) <=> c");
+    }
+
+    #[test]
+    fn associativity() {
+        same("a => b => c", "a => (b => c)");
+        same("a & b & c", "(a & b) & c");
+        same("a | b | c", "(a | b) | c");
+        same("a <=> b <=> c", "(a <=> b) <=> c");
+        same("a <= b <= c", "(a <= b) <= c");
+        assert_ne!(
+            parse_expr("a => b => c").unwrap(),
+            parse_expr("(a => b) => c").unwrap()
+        );
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for f in [
+            "a | b & c",
+            "(a | b) & c",
+            "!(a & b) | !!c",
+            "(a => b) => c",
+            "a => b => c",
+            "(a <=> b) <=> (c <=> d)",
+            "a ^ (b ^ c) nand (d nor !e)",
+            "(a <= b) => c <= d",
+            "true & !false | x1 & req_valid => a[3]",
+        ] {
+            let expr = parse_expr(f).unwrap();
+            assert_eq!(parse_expr(&expr.to_string()).unwrap(), expr, "{f}");
+        }
+    }
+
+    /// The span and message of the error for `source`
+    fn error(source: &str) -> (String, String) {
+        match parse_program(source) {
+            Err(EvalError::Parse { span, message }) => (source[span].to_string(), message),
+            other => panic!("{source}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn recursive_definitions() {
+        let (at, message) = error("f = a & f;");
+        assert_eq!(at, "f");
+        assert_eq!(message, "`f` is defined in terms of itself");
+
+        // points at `gg` in `f`, the first definition of the cycle
+        let (at, message) = error("f = a | gg; h = b; gg = !h & ff; ff = c ^ f;");
+        assert_eq!(at, "gg");
+        assert_eq!(
+            message,
+            "`f` is defined in terms of itself (f -> gg -> ff -> f)"
+        );
+        let source = "f = a | gg; gg = !h & ff; ff = c ^ f;";
+        let Err(EvalError::Parse { span, .. }) = parse_program(source) else {
+            panic!()
+        };
+        assert_eq!(span, 8..10);
+
+        // using a definition twice is no cycle
+        assert!(parse_program("f = g & g; g = a; h = f | g;").is_ok());
+    }
+
+    #[test]
+    fn reserved_words() {
+        let (at, message) = error("and = a;");
+        assert_eq!(at, "and");
+        assert_eq!(message, "`and` is a reserved word, it cannot be defined");
+        let (at, message) = error("f = x | or;");
+        assert_eq!(at, "or");
+        assert_eq!(message, "`or` is a reserved word, it cannot be a variable");
+        let (at, message) = error("f = true[1];");
+        assert_eq!(at, "true[1]");
+        assert_eq!(
+            message,
+            "`true` is a reserved word, it cannot be a variable"
+        );
+        let (at, message) = error("xor[0] = a;");
+        assert_eq!(at, "xor[0]");
+        assert_eq!(message, "`xor` is a reserved word, it cannot be defined");
+        let (at, message) = error("m(a, nand) = a;");
+        assert_eq!(at, "nand");
+        assert_eq!(
+            message,
+            "`nand` is a reserved word, it cannot be a parameter"
+        );
+        // words only starting like reserved ones are names
+        same("android & true_1 & or2", "(android & true_1) & or2");
+    }
+
+    #[test]
+    fn identifiers() {
+        let names = ["x1", "req_valid", "_tmp", "a[3]", "m[0][2]", "x1[10]"];
+        for name in names {
+            assert_eq!(parse_expr(name).unwrap(), Expr::Term(name.to_string()));
+        }
+        assert!(parse_expr("1x").is_err());
+        assert!(parse_expr("a[]").is_err());
+        assert!(parse_expr("a[b]").is_err());
+    }
+
+    #[test]
+    fn function_errors() {
+        let maj = "maj(x, y, z) = x & y | y & z | x & z;";
+        let (at, message) = error(&format!("{maj} f = maj(a, b);"));
+        assert_eq!(at, "maj");
+        assert_eq!(message, "`maj` takes 3 arguments, got 2");
+        let (at, message) = error("m(x) = !x; f = m(a, b);");
+        assert_eq!(
+            (at.as_str(), message.as_str()),
+            ("m", "`m` takes 1 argument, got 2")
+        );
+        // only the functions declared before can be called
+        let (at, message) = error("f = g(a); g(x) = x;");
+        assert_eq!(
+            (at.as_str(), message.as_str()),
+            ("g", "unknown function `g`")
+        );
+        let (at, message) = error("m(x) = x; f = a | m;");
+        assert_eq!(at, "m");
+        assert_eq!(message, "`m` is a function, call it like `m(...)`");
+        let (at, message) = error("m(x) = x & y;");
+        assert_eq!(
+            (at.as_str(), message.as_str()),
+            ("y", "`y` is not a parameter of `m`")
+        );
+        let (at, message) = error("m(x, x) = x;");
+        assert_eq!(at, "x");
+        assert_eq!(message, "`x` is already a parameter of `m`");
+        let source = format!("{maj} f = maj(a, b");
+        let Err(EvalError::Parse { span, message }) = parse_program(&source) else {
+            panic!()
+        };
+        assert_eq!(span, source.len()..source.len());
+        assert_eq!(message, "expected `,` or `)` after the arguments of `maj`");
+    }
+
+    #[test]
+    fn alternative_notations() {
+        for (alt, ascii) in [
+            ("¬a ∧ b ∨ c → d ↔ e ⊕ f", "!a & b | c => d <=> e ^ f"),
+            ("a ⇒ b ⟹ c", "a => b => c"),
+            ("a ⇔ b ⟺ c", "a <=> b <=> c"),
+            ("⊤ ∧ ⊥", "true & false"),
+            ("1 | 0", "true | false"),
+            (
+                "not a and b or c implies d iff e xor f",
+                "!a & b | c => d <=> e ^ f",
+            ),
+            ("a nand b nor c xnor d", "a nand b nor c <=> d"),
+            ("~a && b || c ^ d != e", "!a & b | c ^ d ^ e"),
+            ("!a&&b||c", "!a & b | c"),
+        ] {
+            same(alt, ascii);
+        }
+    }
+
+    #[test]
+    fn words_need_an_end() {
+        // `andy` is a variable, not `and y`
+        for name in ["andy", "ore", "notx", "xor1", "iff_", "nand_b", "implies2"] {
+            assert_eq!(parse_expr(name).unwrap(), Expr::Term(name.to_string()));
+        }
+        assert!(parse_expr("a andy").is_err());
+        same("a and y", "a & y");
+        same("a and(y)", "a & y");
+        same("not(a)", "!a");
+    }
+}
@@ -0,0 +1,885 @@
+use std::collections::HashMap;
+use std::ops::Not;
+
+use crate::tseitin::plaisted_greenbaum;
+use crate::{get_vars, Assignment, Expr};
+
+/// A variable of the [`Solver`], numbered from 0
+pub type Var = usize;
+
+/// A variable or its negation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: Var, positive: bool) -> Lit {
+        Lit(var as u32 * 2 + !positive as u32)
+    }
+
+    pub fn var(self) -> Var {
+        self.0 as usize / 2
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 & 1 == 0
+    }
+
+    // used to index the per-literal tables
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Watch {
+    clause: usize,
+    // some other literal of the clause, if it is true the clause can be skipped
+    blocker: Lit,
+}
+
+struct Clause {
+    lits: Vec<Lit>,
+    learnt: bool,
+    activity: f64,
+    deleted: bool,
+}
+
+/// Max-heap of the unassigned variables, ordered by activity
+#[derive(Default)]
+struct VarHeap {
+    heap: Vec<Var>,
+    // position of each variable in `heap`, if it is in there
+    pos: Vec<Option<usize>>,
+}
+
+impl VarHeap {
+    fn contains(&self, v: Var) -> bool {
+        self.pos[v].is_some()
+    }
+
+    fn insert(&mut self, v: Var, activity: &[f64]) {
+        if v >= self.pos.len() {
+            self.pos.resize(v + 1, None);
+        }
+        if self.contains(v) {
+            return;
+        }
+        self.pos[v] = Some(self.heap.len());
+        self.heap.push(v);
+        self.up(self.heap.len() - 1, activity);
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<Var> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.pos[top] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.pos[last] = Some(0);
+            self.down(0, activity);
+        }
+        Some(top)
+    }
+
+    /// Restores the order after the activity of `v` went up
+    fn bumped(&mut self, v: Var, activity: &[f64]) {
+        if let Some(i) = self.pos[v] {
+            self.up(i, activity);
+        }
+    }
+
+    fn up(&mut self, mut i: usize, activity: &[f64]) {
+        let v = self.heap[i];
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if activity[self.heap[parent]] >= activity[v] {
+                break;
+            }
+            self.heap[i] = self.heap[parent];
+            self.pos[self.heap[i]] = Some(i);
+            i = parent;
+        }
+        self.heap[i] = v;
+        self.pos[v] = Some(i);
+    }
+
+    fn down(&mut self, mut i: usize, activity: &[f64]) {
+        let v = self.heap[i];
+        loop {
+            let left = 2 * i + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.heap.len()
+                && activity[self.heap[right]] > activity[self.heap[left]]
+            {
+                right
+            } else {
+                left
+            };
+            if activity[self.heap[child]] <= activity[v] {
+                break;
+            }
+            self.heap[i] = self.heap[child];
+            self.pos[self.heap[i]] = Some(i);
+            i = child;
+        }
+        self.heap[i] = v;
+        self.pos[v] = Some(i);
+    }
+}
+
+/// The `i`th element (from 0) of the Luby sequence 1 1 2 1 1 2 4 1 1 2 ...
+fn luby(mut i: u64) -> u64 {
+    let mut size = 1;
+    let mut seq = 0;
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+// conflicts in the first restart interval, scaled by the Luby sequence after
+const RESTART_BASE: u64 = 100;
+const VAR_DECAY: f64 = 0.95;
+const CLAUSE_DECAY: f64 = 0.999;
+
+/// A CDCL SAT solver, with two watched literals, first-UIP clause learning,
+/// VSIDS branching, phase saving and Luby restarts
+///
+/// Clauses can be added between calls to [`Solver::solve`].
+pub struct Solver {
+    clauses: Vec<Clause>,
+    // clauses watching each literal, indexed by `Lit::index`
+    watches: Vec<Vec<Watch>>,
+    values: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    // the last value of each variable, tried first when branching on it again
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    var_inc: f64,
+    clause_inc: f64,
+    heap: VarHeap,
+    trail: Vec<Lit>,
+    // where each decision level starts in `trail`
+    trail_lim: Vec<usize>,
+    // how much of `trail` has been propagated
+    qhead: usize,
+    seen: Vec<bool>,
+    learnts: usize,
+    max_learnts: usize,
+    // false once the clauses are known to be unsatisfiable
+    ok: bool,
+    model: Vec<bool>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver {
+            clauses: vec![],
+            watches: vec![],
+            values: vec![],
+            level: vec![],
+            reason: vec![],
+            phase: vec![],
+            activity: vec![],
+            var_inc: 1.0,
+            clause_inc: 1.0,
+            heap: VarHeap::default(),
+            trail: vec![],
+            trail_lim: vec![],
+            qhead: 0,
+            seen: vec![],
+            learnts: 0,
+            max_learnts: 1000,
+            ok: true,
+            model: vec![],
+        }
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn new_var(&mut self) -> Var {
+        let v = self.values.len();
+        self.values.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.phase.push(false);
+        self.activity.push(0.0);
+        self.seen.push(false);
+        self.watches.push(vec![]);
+        self.watches.push(vec![]);
+        self.heap.insert(v, &self.activity);
+        v
+    }
+
+    /// Adds a clause (a disjunction of literals), creating any variables it mentions.
+    /// Returns false if the clauses are now known to be unsatisfiable.
+    pub fn add_clause(&mut self, lits: &[Lit]) -> bool {
+        if !self.ok {
+            return false;
+        }
+        if let Some(max) = lits.iter().map(|l| l.var()).max() {
+            while self.num_vars() <= max {
+                self.new_var();
+            }
+        }
+        self.cancel_until(0);
+
+        let mut lits = lits.to_vec();
+        lits.sort();
+        lits.dedup();
+        // already satisfied or containing both `x` and `!x`
+        if lits.windows(2).any(|w| w[0] == !w[1])
+            || lits.iter().any(|l| self.value(*l) == Some(true))
+        {
+            return true;
+        }
+        lits.retain(|l| self.value(*l).is_none());
+
+        match lits.len() {
+            0 => self.ok = false,
+            1 => {
+                self.enqueue(lits[0], None);
+                self.ok = self.propagate().is_none();
+            }
+            _ => {
+                self.attach(lits, false);
+            }
+        }
+        self.ok
+    }
+
+    /// Searches for an assignment satisfying all the clauses, see [`Solver::model`]
+    pub fn solve(&mut self) -> bool {
+        if !self.ok {
+            return false;
+        }
+        let mut restarts = 0;
+        loop {
+            match self.search(RESTART_BASE * luby(restarts)) {
+                Some(sat) => {
+                    if sat {
+                        self.model = self.values.iter().map(|v| v.unwrap_or(false)).collect();
+                    } else {
+                        self.ok = false;
+                    }
+                    self.cancel_until(0);
+                    return sat;
+                }
+                None => restarts += 1,
+            }
+        }
+    }
+
+    /// The assignment found by the last successful [`Solver::solve`], indexed by variable
+    pub fn model(&self) -> &[bool] {
+        &self.model
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.values[lit.var()].map(|v| v == lit.is_positive())
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    /// Runs until a result or until `max_conflicts` conflicts, then gives up for a restart
+    fn search(&mut self, max_conflicts: u64) -> Option<bool> {
+        let mut conflicts = 0;
+        loop {
+            if let Some(confl) = self.propagate() {
+                conflicts += 1;
+                if self.decision_level() == 0 {
+                    return Some(false);
+                }
+                let (learnt, backtrack) = self.analyze(confl);
+                self.cancel_until(backtrack);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let first = learnt[0];
+                    let ci = self.attach(learnt, true);
+                    self.bump_clause(ci);
+                    self.enqueue(first, Some(ci));
+                }
+                self.var_inc /= VAR_DECAY;
+                self.clause_inc /= CLAUSE_DECAY;
+                continue;
+            }
+
+            if conflicts >= max_conflicts {
+                self.cancel_until(0);
+                return None;
+            }
+            if self.learnts >= self.max_learnts + self.trail.len() {
+                self.reduce_learnts();
+            }
+
+            let Some(v) = self.pick_branch_var() else {
+                return Some(true);
+            };
+            self.trail_lim.push(self.trail.len());
+            self.enqueue(Lit::new(v, self.phase[v]), None);
+        }
+    }
+
+    fn pick_branch_var(&mut self) -> Option<Var> {
+        while let Some(v) = self.heap.pop(&self.activity) {
+            if self.values[v].is_none() {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    fn attach(&mut self, lits: Vec<Lit>, learnt: bool) -> usize {
+        let ci = self.clauses.len();
+        self.watches[lits[0].index()].push(Watch {
+            clause: ci,
+            blocker: lits[1],
+        });
+        self.watches[lits[1].index()].push(Watch {
+            clause: ci,
+            blocker: lits[0],
+        });
+        self.clauses.push(Clause {
+            lits,
+            learnt,
+            activity: 0.0,
+            deleted: false,
+        });
+        if learnt {
+            self.learnts += 1;
+        }
+        ci
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let v = lit.var();
+        self.values[v] = Some(lit.is_positive());
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(lit);
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for lit in self.trail.drain(start..) {
+            let v = lit.var();
+            self.phase[v] = lit.is_positive();
+            self.values[v] = None;
+            self.reason[v] = None;
+            self.heap.insert(v, &self.activity);
+        }
+        self.trail_lim.truncate(level);
+        self.qhead = self.qhead.min(start);
+    }
+
+    /// Propagates the unit clauses, returning the clause that became false if any
+    fn propagate(&mut self) -> Option<usize> {
+        let value =
+            |values: &[Option<bool>], lit: Lit| values[lit.var()].map(|v| v == lit.is_positive());
+        while self.qhead < self.trail.len() {
+            let false_lit = !self.trail[self.qhead];
+            self.qhead += 1;
+
+            // the watches that stay are moved to the front of the list
+            let mut ws = std::mem::take(&mut self.watches[false_lit.index()]);
+            let (mut i, mut j) = (0, 0);
+            let mut conflict = None;
+            while i < ws.len() {
+                let w = ws[i];
+                i += 1;
+                if value(&self.values, w.blocker) == Some(true) {
+                    ws[j] = w;
+                    j += 1;
+                    continue;
+                }
+                let clause = &mut self.clauses[w.clause];
+                // keep the false literal second, the first one may still be true
+                let lits = &mut clause.lits;
+                if lits[0] == false_lit {
+                    lits.swap(0, 1);
+                }
+                let first = lits[0];
+                let watch = Watch {
+                    clause: w.clause,
+                    blocker: first,
+                };
+                if value(&self.values, first) == Some(true) {
+                    ws[j] = watch;
+                    j += 1;
+                    continue;
+                }
+
+                let replacement =
+                    (2..lits.len()).find(|k| value(&self.values, lits[*k]) != Some(false));
+                if let Some(k) = replacement {
+                    lits.swap(1, k);
+                    let new_watch = lits[1];
+                    self.watches[new_watch.index()].push(watch);
+                    continue;
+                }
+
+                ws[j] = watch;
+                j += 1;
+                if value(&self.values, first) == Some(false) {
+                    conflict = Some(w.clause);
+                    while i < ws.len() {
+                        ws[j] = ws[i];
+                        i += 1;
+                        j += 1;
+                    }
+                    break;
+                }
+                self.enqueue(first, Some(w.clause));
+            }
+            ws.truncate(j);
+            self.watches[false_lit.index()] = ws;
+            if conflict.is_some() {
+                self.qhead = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// Learns the first-UIP clause from a conflict, returns it with the level to go back to
+    fn analyze(&mut self, mut confl: usize) -> (Vec<Lit>, usize) {
+        // the asserting literal goes to the front once it is known
+        let mut learnt = vec![Lit(0)];
+        let mut open = 0;
+        let mut idx = self.trail.len();
+        let mut p = None;
+
+        loop {
+            if self.clauses[confl].learnt {
+                self.bump_clause(confl);
+            }
+            // the first literal of a reason clause is the one it implied
+            let skip = p.is_some() as usize;
+            for k in skip..self.clauses[confl].lits.len() {
+                let q = self.clauses[confl].lits[k];
+                let v = q.var();
+                if self.seen[v] || self.level[v] == 0 {
+                    continue;
+                }
+                self.seen[v] = true;
+                self.bump_var(v);
+                if self.level[v] >= self.decision_level() {
+                    open += 1;
+                } else {
+                    learnt.push(q);
+                }
+            }
+
+            // the next literal of this level to resolve on
+            loop {
+                idx -= 1;
+                if self.seen[self.trail[idx].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[idx];
+            self.seen[lit.var()] = false;
+            p = Some(lit);
+            open -= 1;
+            if open == 0 {
+                break;
+            }
+            confl = self.reason[lit.var()].expect("only decisions have no reason");
+        }
+        learnt[0] = !p.unwrap();
+        self.minimize(&mut learnt);
+
+        // the literal assigned last (other than the asserting one) is watched second
+        let mut backtrack = 0;
+        if learnt.len() > 1 {
+            let max = (1..learnt.len())
+                .max_by_key(|k| self.level[learnt[*k].var()])
+                .unwrap();
+            learnt.swap(1, max);
+            backtrack = self.level[learnt[1].var()];
+        }
+        (learnt, backtrack)
+    }
+
+    /// Drops the literals implied by the rest of the learnt clause, expects all
+    /// the variables of `learnt` other than the first to be marked as seen
+    fn minimize(&mut self, learnt: &mut Vec<Lit>) {
+        // a cheap filter, only literals from these levels can be implied by the others
+        let levels = learnt[1..]
+            .iter()
+            .fold(0u64, |acc, l| acc | 1 << (self.level[l.var()] % 64));
+        let mut to_clear = learnt.iter().map(|l| l.var()).collect::<Vec<_>>();
+        let mut k = 1;
+        while k < learnt.len() {
+            let lit = learnt[k];
+            if self.reason[lit.var()].is_some() && self.implied(lit, levels, &mut to_clear) {
+                learnt.swap_remove(k);
+            } else {
+                k += 1;
+            }
+        }
+        for v in to_clear {
+            self.seen[v] = false;
+        }
+    }
+
+    /// Whether the (false) literal `p` follows from the seen literals, marking
+    /// the ones that turn out to follow from them as well
+    fn implied(&mut self, p: Lit, levels: u64, to_clear: &mut Vec<Var>) -> bool {
+        let mut stack = vec![p];
+        let top = to_clear.len();
+        while let Some(q) = stack.pop() {
+            let r = self.reason[q.var()].expect("only implied literals are pushed");
+            for k in 1..self.clauses[r].lits.len() {
+                let l = self.clauses[r].lits[k];
+                let v = l.var();
+                if self.seen[v] || self.level[v] == 0 {
+                    continue;
+                }
+                if self.reason[v].is_none() || levels & (1 << (self.level[v] % 64)) == 0 {
+                    for v in to_clear.drain(top..) {
+                        self.seen[v] = false;
+                    }
+                    return false;
+                }
+                self.seen[v] = true;
+                stack.push(l);
+                to_clear.push(v);
+            }
+        }
+        true
+    }
+
+    fn bump_var(&mut self, v: Var) {
+        self.activity[v] += self.var_inc;
+        if self.activity[v] > 1e100 {
+            for a in &mut self.activity {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+        self.heap.bumped(v, &self.activity);
+    }
+
+    fn bump_clause(&mut self, ci: usize) {
+        self.clauses[ci].activity += self.clause_inc;
+        if self.clauses[ci].activity > 1e20 {
+            for c in &mut self.clauses {
+                c.activity *= 1e-20;
+            }
+            self.clause_inc *= 1e-20;
+        }
+    }
+
+    /// Forgets the less active half of the learnt clauses
+    fn reduce_learnts(&mut self) {
+        let locked = |s: &Solver, ci: usize| {
+            let first = s.clauses[ci].lits[0];
+            s.reason[first.var()] == Some(ci) && s.value(first) == Some(true)
+        };
+        let mut learnt = (0..self.clauses.len())
+            .filter(|ci| {
+                let c = &self.clauses[*ci];
+                c.learnt && !c.deleted && c.lits.len() > 2
+            })
+            .collect::<Vec<_>>();
+        learnt.sort_by(|a, b| {
+            self.clauses[*a]
+                .activity
+                .total_cmp(&self.clauses[*b].activity)
+        });
+        for ci in learnt.iter().take(learnt.len() / 2) {
+            if !locked(self, *ci) {
+                self.clauses[*ci].deleted = true;
+                self.learnts -= 1;
+            }
+        }
+
+        // move the remaining clauses together and renumber everything pointing at them
+        let mut renumbered = vec![usize::MAX; self.clauses.len()];
+        let clauses = std::mem::take(&mut self.clauses);
+        for (ci, c) in clauses.into_iter().enumerate() {
+            if !c.deleted {
+                renumbered[ci] = self.clauses.len();
+                self.clauses.push(c);
+            }
+        }
+        for ci in self.reason.iter_mut().flatten() {
+            *ci = renumbered[*ci];
+        }
+        for ws in &mut self.watches {
+            ws.retain_mut(|w| {
+                w.clause = renumbered[w.clause];
+                w.clause != usize::MAX
+            });
+        }
+        self.max_learnts += self.max_learnts / 10;
+    }
+}
+
+/// Finds an assignment making `expr` true using the SAT solver
+///
+/// Unlike tabulating, this copes with thousands of variables.
+pub fn satisfy(expr: &Expr) -> Option<HashMap<String, bool>> {
+    let encoding = plaisted_greenbaum(expr);
+    let mut solver = Solver::new();
+    for clause in &encoding.clauses {
+        solver.add_clause(clause);
+    }
+    if !solver.add_clause(&[encoding.root]) || !solver.solve() {
+        return None;
+    }
+    let model = solver.model();
+    // the variables without a solver variable cannot change the value of `expr`, so
+    // they are simply false
+    Some(
+        get_vars(expr)
+            .into_iter()
+            .map(|name| {
+                let value = encoding.vars.get(&name).is_some_and(|v| model[*v]);
+                (name, value)
+            })
+            .collect(),
+    )
+}
+
+/// Finds an assignment making `expr` false, there is none if it is a tautology
+pub fn falsify(expr: &Expr) -> Option<HashMap<String, bool>> {
+    satisfy(&Expr::Not(expr.clone().into()))
+}
+
+/// The assignments making a formula true, found one at a time, see [`models`]
+pub struct Models {
+    solver: Solver,
+    /// The variables of the assignments with their solver variables
+    vars: Vec<(String, Var)>,
+    done: bool,
+}
+
+impl Iterator for Models {
+    type Item = Assignment;
+
+    fn next(&mut self) -> Option<Assignment> {
+        if self.done || !self.solver.solve() {
+            self.done = true;
+            return None;
+        }
+        let model = self.solver.model();
+        let assignment = self
+            .vars
+            .iter()
+            .map(|(name, v)| (name.clone(), model[*v]))
+            .collect();
+        // the next model has to differ from this one on some variable
+        let blocking = self
+            .vars
+            .iter()
+            .map(|(_, v)| Lit::new(*v, !model[*v]))
+            .collect::<Vec<_>>();
+        self.done = !self.solver.add_clause(&blocking);
+        Some(assignment)
+    }
+}
+
+/// Every assignment of the variables of `expr`, or of `projection` if given, for which
+/// `expr` can be true, without tabulating
+///
+/// Each one is found by the SAT solver, which is then told to avoid it, so the solver
+/// only runs as far as the iterator is consumed. The variables missing from the clauses,
+/// like the ones of `projection` which are not in `expr`, take both values.
+pub fn models(expr: &Expr, projection: Option<&[String]>) -> Models {
+    let encoding = plaisted_greenbaum(expr);
+    let mut solver = Solver::new();
+    for clause in &encoding.clauses {
+        solver.add_clause(clause);
+    }
+    let done = !solver.add_clause(&[encoding.root]);
+    while solver.num_vars() < encoding.num_vars {
+        solver.new_var();
+    }
+    let names = match projection {
+        Some(names) => names.to_vec(),
+        None => get_vars(expr).into_iter().collect(),
+    };
+    let mut vars = names
+        .into_iter()
+        .map(|name| {
+            let v = match encoding.vars.get(&name) {
+                Some(v) => *v,
+                None => solver.new_var(),
+            };
+            (name, v)
+        })
+        .collect::<Vec<_>>();
+    vars.sort();
+    vars.dedup();
+    Models { solver, vars, done }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_program;
+    use crate::testing::Rng;
+
+    fn random_cnf(rng: &mut Rng, vars: usize, clauses: usize) -> Vec<Vec<Lit>> {
+        (0..clauses)
+            .map(|_| {
+                let len = 1 + rng.below(3) as usize;
+                (0..len)
+                    .map(|_| Lit::new(rng.below(vars as u64) as Var, rng.below(2) == 0))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn satisfies(model: &[bool], clauses: &[Vec<Lit>]) -> bool {
+        clauses
+            .iter()
+            .all(|c| c.iter().any(|l| model[l.var()] == l.is_positive()))
+    }
+
+    /// The number of assignments of `vars` variables satisfying `clauses`
+    fn brute_force(vars: usize, clauses: &[Vec<Lit>]) -> usize {
+        (0..1 << vars)
+            .filter(|bits| {
+                let model = (0..vars).map(|v| bits >> v & 1 == 1).collect::<Vec<_>>();
+                satisfies(&model, clauses)
+            })
+            .count()
+    }
+
+    /// A solver keeping few learnt clauses, so that they are reduced often
+    fn solver(vars: usize, clauses: &[Vec<Lit>]) -> Solver {
+        let mut solver = Solver::new();
+        solver.max_learnts = 10;
+        while solver.num_vars() < vars {
+            solver.new_var();
+        }
+        for c in clauses {
+            solver.add_clause(c);
+        }
+        solver
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..300 {
+            let vars = 1 + rng.below(12) as usize;
+            let count = rng.below(5 * vars as u64) as usize;
+            let clauses = random_cnf(&mut rng, vars, count);
+            let expected = brute_force(vars, &clauses);
+
+            // every model is blocked once found, so the solver has to find them all
+            let mut solver = solver(vars, &clauses);
+            let mut found = 0;
+            while solver.solve() {
+                let model = solver.model().to_vec();
+                assert!(satisfies(&model, &clauses), "{clauses:?}");
+                found += 1;
+                let blocking = (0..vars)
+                    .map(|v| Lit::new(v, !model[v]))
+                    .collect::<Vec<_>>();
+                if !solver.add_clause(&blocking) {
+                    break;
+                }
+            }
+            assert_eq!(found, expected, "{clauses:?}");
+        }
+    }
+
+    /// Clauses putting every pigeon in a hole, with at most one pigeon per hole
+    fn pigeonhole(pigeons: usize, holes: usize) -> Vec<Vec<Lit>> {
+        // pigeon `i` is in hole `j`
+        let p = |i: usize, j: usize| Lit::new(i * holes + j, true);
+        let mut clauses = (0..pigeons)
+            .map(|i| (0..holes).map(|j| p(i, j)).collect())
+            .collect::<Vec<_>>();
+        for j in 0..holes {
+            for i in 0..pigeons {
+                for k in i + 1..pigeons {
+                    clauses.push(vec![!p(i, j), !p(k, j)]);
+                }
+            }
+        }
+        clauses
+    }
+
+    #[test]
+    fn pigeons_do_not_fit() {
+        assert!(!solver(7 * 6, &pigeonhole(7, 6)).solve());
+
+        let clauses = pigeonhole(6, 6);
+        let mut solver = solver(6 * 6, &clauses);
+        assert!(solver.solve());
+        assert!(satisfies(solver.model(), &clauses));
+    }
+
+    #[test]
+    fn models_keep_unused_parameters() {
+        let program = parse_program("m(x, y) = x; f = m(a, b);").unwrap();
+        let f = program.definition("f").unwrap();
+        let found = models(f, None).collect::<Vec<_>>();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|m| m["a"] && m.contains_key("b")));
+        assert_eq!(satisfy(f).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn projected_models_are_distinct() {
+        let expr = crate::parse_expr("a & b | c & !d | e").unwrap();
+        let names = |vars: &[&str]| vars.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let projected = |vars: &[&str]| {
+            let mut found = models(&expr, Some(&names(vars)))
+                .map(|m| vars.iter().map(|v| m[*v]).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let all = found.len();
+            found.sort();
+            found.dedup();
+            assert_eq!(found.len(), all, "{vars:?}");
+            found
+        };
+        // `e` makes every value of the others a model
+        assert_eq!(projected(&["a", "b"]).len(), 4);
+        assert_eq!(projected(&["b", "a", "b"]).len(), 4);
+        // `z` is not in the formula
+        assert_eq!(projected(&["e", "z"]).len(), 4);
+        assert_eq!(projected(&[]), [Vec::<bool>::new()]);
+        assert_eq!(models(&expr, None).count(), 23);
+        let none = crate::parse_expr("a & !a & b").unwrap();
+        assert_eq!(models(&none, Some(&names(&["b"]))).count(), 0);
+    }
+}
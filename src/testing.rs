@@ -0,0 +1,37 @@
+//! Helpers shared by the tests
+
+use crate::{parse_expr, Expr};
+
+/// A xorshift generator, so that the random tests are the same on every run
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number below `n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    /// A chain of `len` variables out of the first `vars` letters, randomly negated and
+    /// joined by random binary connectives
+    pub fn formula(&mut self, vars: u8, len: usize) -> Expr {
+        let ops = ["&", "|", "^", "=>", "<=>", "nand", "nor", "<="];
+        let mut f = String::new();
+        for i in 0..len {
+            if i > 0 {
+                f += &format!(" {} ", ops[self.below(ops.len() as u64) as usize]);
+            }
+            if self.below(2) == 0 {
+                f += "!";
+            }
+            f.push((b'a' + self.below(vars as u64) as u8) as char);
+        }
+        parse_expr(&f).unwrap()
+    }
+}
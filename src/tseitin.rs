@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+
+use crate::sat::{Lit, Var};
+use crate::Expr;
+
+/// Clauses satisfiable exactly when a formula is, see [`tseitin`] and [`plaisted_greenbaum`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoding {
+    pub clauses: Vec<Vec<Lit>>,
+    /// The solver variable standing for each variable of the formula
+    ///
+    /// The variables only passed to parameters that a connective ignores, like `b` in
+    /// `m(a, b)` after `m(x, y) = x;`, are not in the clauses and have none.
+    pub vars: HashMap<String, Var>,
+    /// The literal standing for the whole formula, it has to be made true separately
+    pub root: Lit,
+    /// The number of solver variables used, including the auxiliary ones
+    pub num_vars: usize,
+    // the variable already defined for each gate, so repeated subexpressions share it,
+    // with the directions of the definition emitted so far
+    gates: HashMap<(Gate, Lit, Lit), (Lit, Polarity)>,
+    // the connective and operands of the subexpression named by each auxiliary variable,
+    // from which [`Encoding::aux`] rebuilds it, as copying it for every gate is quadratic
+    aux: HashMap<Var, (Connective, Lit, Lit)>,
+    true_lit: Option<Lit>,
+    full: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Gate {
+    And,
+    Or,
+    Equiv,
+}
+
+/// The connective of a subexpression named by an auxiliary variable, the negated ones
+/// are named by the variable of their positive counterpart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Connective {
+    And,
+    Or,
+    Imply,
+    Converse,
+    Equiv,
+}
+
+/// Which directions of `x <=> gate` are needed for a subexpression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Polarity {
+    /// Only ever made true, `x => gate` is enough
+    Positive,
+    /// Only ever made false, `gate => x` is enough
+    Negative,
+    Both,
+}
+
+impl Polarity {
+    fn flip(self) -> Polarity {
+        match self {
+            Polarity::Positive => Polarity::Negative,
+            Polarity::Negative => Polarity::Positive,
+            Polarity::Both => Polarity::Both,
+        }
+    }
+
+    fn union(self, other: Polarity) -> Polarity {
+        if self == other {
+            self
+        } else {
+            Polarity::Both
+        }
+    }
+
+    fn positive(self) -> bool {
+        self != Polarity::Negative
+    }
+
+    fn negative(self) -> bool {
+        self != Polarity::Positive
+    }
+}
+
+/// Encodes `expr` into clauses with a fresh variable for every operator, which keeps
+/// the size linear where converting to CNF directly can blow up exponentially
+///
+/// Each auxiliary variable is equivalent to the subexpression it names.
+pub fn tseitin(expr: &Expr) -> Encoding {
+    Encoding::new(expr, true)
+}
+
+/// Like [`tseitin`], but only defines each auxiliary variable in the direction the
+/// polarity of its subexpression needs, which leaves out about half of the clauses
+///
+/// The auxiliary variables only imply (or are implied by) their subexpressions, so the
+/// encoding is equisatisfiable with `expr` only once `root` is made true.
+pub fn plaisted_greenbaum(expr: &Expr) -> Encoding {
+    Encoding::new(expr, false)
+}
+
+impl Encoding {
+    fn new(expr: &Expr, full: bool) -> Encoding {
+        let mut encoding = Encoding {
+            clauses: vec![],
+            vars: HashMap::new(),
+            aux: HashMap::new(),
+            root: Lit::new(0, true),
+            num_vars: 0,
+            gates: HashMap::new(),
+            true_lit: None,
+            full,
+        };
+        encoding.root = encoding.encode(expr, Polarity::Positive);
+        encoding
+    }
+
+    fn fresh(&mut self) -> Lit {
+        self.num_vars += 1;
+        Lit::new(self.num_vars - 1, true)
+    }
+
+    /// A literal that is always true
+    fn constant(&mut self) -> Lit {
+        if let Some(x) = self.true_lit {
+            return x;
+        }
+        let x = self.fresh();
+        self.clauses.push(vec![x]);
+        self.true_lit = Some(x);
+        x
+    }
+
+    fn encode(&mut self, expr: &Expr, pol: Polarity) -> Lit {
+        let (gate, a, b) = match expr {
+            Expr::True => return self.constant(),
+            Expr::False => return !self.constant(),
+            Expr::Term(t) => {
+                if let Some(v) = self.vars.get(t) {
+                    return Lit::new(*v, true);
+                }
+                let x = self.fresh();
+                self.vars.insert(t.clone(), x.var());
+                return x;
+            }
+            Expr::Not(e) => return !self.encode(e, pol.flip()),
+            Expr::Apply(m, args) => return self.encode(&m.apply(args), pol),
+            Expr::And(l, r) => (Gate::And, self.encode(l, pol), self.encode(r, pol)),
+            Expr::Or(l, r) => (Gate::Or, self.encode(l, pol), self.encode(r, pol)),
+            Expr::Imply(l, r) => {
+                let (a, b) = (self.encode(l, pol.flip()), self.encode(r, pol));
+                return self.gate(Gate::Or, !a, b, pol, (Connective::Imply, a, b));
+            }
+            Expr::Equiv(l, r) => (
+                Gate::Equiv,
+                self.encode(l, Polarity::Both),
+                self.encode(r, Polarity::Both),
+            ),
+            Expr::Converse(l, r) => {
+                let (a, b) = (self.encode(l, pol), self.encode(r, pol.flip()));
+                return self.gate(Gate::Or, a, !b, pol, (Connective::Converse, a, b));
+            }
+            // the negated connectives are the negations of the gates for the others
+            Expr::Xor(l, r) => {
+                let (a, b) = (
+                    self.encode(l, Polarity::Both),
+                    self.encode(r, Polarity::Both),
+                );
+                return !self.gate(Gate::Equiv, a, b, pol.flip(), (Connective::Equiv, a, b));
+            }
+            Expr::Nand(l, r) => {
+                let (a, b) = (self.encode(l, pol.flip()), self.encode(r, pol.flip()));
+                return !self.gate(Gate::And, a, b, pol.flip(), (Connective::And, a, b));
+            }
+            Expr::Nor(l, r) => {
+                let (a, b) = (self.encode(l, pol.flip()), self.encode(r, pol.flip()));
+                return !self.gate(Gate::Or, a, b, pol.flip(), (Connective::Or, a, b));
+            }
+        };
+        let connective = match gate {
+            Gate::And => Connective::And,
+            Gate::Or => Connective::Or,
+            Gate::Equiv => Connective::Equiv,
+        };
+        self.gate(gate, a, b, pol, (connective, a, b))
+    }
+
+    /// The subexpression named by the auxiliary variable `var`, `None` for the variables
+    /// of the formula
+    ///
+    /// Double negations are left out, and a subexpression appearing several times is
+    /// given as it was first seen.
+    pub fn aux(&self, var: Var) -> Option<Expr> {
+        if self.true_lit.is_some_and(|x| x.var() == var) {
+            return Some(Expr::True);
+        }
+        self.aux.get(&var)?;
+        let names = self.vars.iter().map(|(name, v)| (*v, name)).collect();
+        Some(self.rebuild(Lit::new(var, true), &names))
+    }
+
+    fn rebuild(&self, lit: Lit, names: &HashMap<Var, &String>) -> Expr {
+        let expr = if self.true_lit.is_some_and(|x| x.var() == lit.var()) {
+            return if lit.is_positive() {
+                Expr::True
+            } else {
+                Expr::False
+            };
+        } else if let Some(name) = names.get(&lit.var()) {
+            Expr::Term(name.to_string())
+        } else {
+            let (connective, a, b) = self.aux[&lit.var()];
+            let (l, r) = (self.rebuild(a, names).into(), self.rebuild(b, names).into());
+            match connective {
+                Connective::And => Expr::And(l, r),
+                Connective::Or => Expr::Or(l, r),
+                Connective::Imply => Expr::Imply(l, r),
+                Connective::Converse => Expr::Converse(l, r),
+                Connective::Equiv => Expr::Equiv(l, r),
+            }
+        };
+        if lit.is_positive() {
+            expr
+        } else {
+            Expr::Not(expr.into())
+        }
+    }
+
+    fn gate(
+        &mut self,
+        gate: Gate,
+        a: Lit,
+        b: Lit,
+        pol: Polarity,
+        named: (Connective, Lit, Lit),
+    ) -> Lit {
+        let pol = if self.full { Polarity::Both } else { pol };
+        // all the gates are commutative
+        let key = (gate, a.min(b), a.max(b));
+        let (x, done) = match self.gates.get(&key) {
+            Some(&(x, done)) if done.union(pol) == done => return x,
+            Some(&(x, done)) => (x, Some(done)),
+            None => {
+                let x = self.fresh();
+                self.aux.insert(x.var(), named);
+                (x, None)
+            }
+        };
+        let positive = pol.positive() && !done.is_some_and(Polarity::positive);
+        let negative = pol.negative() && !done.is_some_and(Polarity::negative);
+        match gate {
+            Gate::And if positive => {
+                self.clauses.push(vec![!x, a]);
+                self.clauses.push(vec![!x, b]);
+            }
+            Gate::Or if positive => self.clauses.push(vec![!x, a, b]),
+            Gate::Equiv if positive => {
+                self.clauses.push(vec![!x, !a, b]);
+                self.clauses.push(vec![!x, a, !b]);
+            }
+            _ => {}
+        }
+        match gate {
+            Gate::And if negative => self.clauses.push(vec![x, !a, !b]),
+            Gate::Or if negative => {
+                self.clauses.push(vec![x, !a]);
+                self.clauses.push(vec![x, !b]);
+            }
+            Gate::Equiv if negative => {
+                self.clauses.push(vec![x, a, b]);
+                self.clauses.push(vec![x, !a, !b]);
+            }
+            _ => {}
+        }
+        self.gates
+            .insert(key, (x, done.map_or(pol, |d| d.union(pol))));
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+    use crate::{eval, get_vars, parse_expr};
+
+    /// The assignments of the variables of `expr` which some model of the encoding has,
+    /// with the root made true, found by trying every value of every solver variable
+    fn projected_models(expr: &Expr, encoding: &Encoding) -> Vec<Vec<bool>> {
+        let mut names = get_vars(expr).into_iter().collect::<Vec<_>>();
+        names.sort();
+        let mut found = (0..1u32 << encoding.num_vars)
+            .map(|bits| {
+                (0..encoding.num_vars)
+                    .map(|v| bits >> v & 1 == 1)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|model| {
+                let holds = |l: &Lit| model[l.var()] == l.is_positive();
+                holds(&encoding.root) && encoding.clauses.iter().all(|c| c.iter().any(holds))
+            })
+            .map(|model| {
+                names
+                    .iter()
+                    .map(|n| encoding.vars.get(n).is_some_and(|v| model[*v]))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        found.dedup();
+        found
+    }
+
+    /// The assignments of the variables of `expr` making it true
+    fn models(expr: &Expr) -> Vec<Vec<bool>> {
+        let mut names = get_vars(expr).into_iter().collect::<Vec<_>>();
+        names.sort();
+        let mut found = crate::make_table(&names)
+            .into_iter()
+            .filter(|row| eval(expr, row).unwrap())
+            .map(|row| names.iter().map(|n| row[n]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn encodings_keep_the_models() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+        let mut exprs = (0..300).map(|_| rng.formula(4, 5)).collect::<Vec<_>>();
+        exprs.extend(
+            [
+                "a & !a",
+                "a | true",
+                "!(a => false) <=> b",
+                "a & b ^ !(b & a)",
+            ]
+            .map(|f| parse_expr(f).unwrap()),
+        );
+        for expr in exprs {
+            let expected = models(&expr);
+            assert_eq!(projected_models(&expr, &tseitin(&expr)), expected, "{expr}");
+            let pg = plaisted_greenbaum(&expr);
+            assert_eq!(projected_models(&expr, &pg), expected, "{expr}");
+            assert!(pg.clauses.len() <= tseitin(&expr).clauses.len(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn gates_are_shared() {
+        // `b & a`, `a nand b` and `a & b` are one gate
+        let encoding = tseitin(&parse_expr("(a & b | b & a) ^ (a nand b)").unwrap());
+        // `a`, `b`, the and, the or and the xor
+        assert_eq!(encoding.num_vars, 5);
+        // a gate used with both polarities is defined in both directions once
+        let encoding = plaisted_greenbaum(&parse_expr("(a & b) => (a & b) | c").unwrap());
+        assert_eq!(encoding.num_vars, 6);
+        assert_eq!(encoding.clauses.len(), 3 + 1 + 1);
+    }
+
+    #[test]
+    fn aux_names_subexpressions() {
+        let expr = |f: &str| parse_expr(f).unwrap();
+        let encoding = tseitin(&expr("(a nand b) | (c nor !d) | (a ^ c) | true"));
+        let aux = |l: Lit| (l.is_positive(), encoding.aux(l.var()));
+        for name in ["a", "b", "c", "d"] {
+            assert_eq!(encoding.aux(encoding.vars[name]), None);
+        }
+        // the negated connectives are named by their positive counterparts
+        let mut found = (0..encoding.num_vars)
+            .filter_map(|v| encoding.aux(v))
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        found.sort();
+        let mut expected = [
+            "!(a & b) | !(c | !d)",
+            "!(a & b) | !(c | !d) | !(a <=> c)",
+            "!(a & b) | !(c | !d) | !(a <=> c) | true",
+            "a & b",
+            "a <=> c",
+            "c | !d",
+            "true",
+        ];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(
+            aux(encoding.root),
+            (true, Some(expr("!(a & b) | !(c | !d) | !(a <=> c) | true")))
+        );
+        let root = tseitin(&expr("a nand b")).root;
+        assert_eq!(
+            (
+                root.is_positive(),
+                tseitin(&expr("a nand b")).aux(root.var())
+            ),
+            (false, Some(expr("a & b")))
+        );
+    }
+}